@@ -1,15 +1,27 @@
-
-const FOG_MIN_DISTANCE: f32 = 384.0;
-const FOG_COLOR: vec4<f32> = vec4<f32>(0.4, 0.4, 0.4, 1.0);
-
-// 
-fn ffog_calc_factor(clamped_d: f32, fog_distance: f32, chunk_size: f32) -> f32 {
-    let fog_max: f32 = fog_distance;
-    let fog_min: f32 = fog_max - 4.5 * chunk_size;
-    let clamped_d = clamp(fog_min, clamped_d, fog_max);
-    return 1.0 - (fog_max - clamped_d) / (fog_max - fog_min);
+// Returns how much of the fog covers a fragment at the specified distance from the camera,
+// with the same formulas as `bevy_pbr::fog` so the terrain fades like the other meshes.
+// The falloff modes are numbered as `GpuDistanceFog::FALLOFF_*`.
+fn terrain_fog_factor(distance: f32) -> f32 {
+    var factor: f32;
+    switch terrain_fog.falloff {
+        // exponential.
+        case 1u: {
+            factor = 1.0 - 1.0 / exp(distance * terrain_fog.density);
+        }
+        // exponential squared.
+        case 2u: {
+            let distance_times_density = distance * terrain_fog.density;
+            factor = 1.0 - 1.0 / exp(distance_times_density * distance_times_density);
+        }
+        // linear.
+        default: {
+            factor = 1.0 - clamp((terrain_fog.end - distance) / (terrain_fog.end - terrain_fog.start), 0.0, 1.0);
+        }
+    }
+    return factor;
 }
 
-fn ffog_apply_fog(d: f32, fog_min: f32, chunk_size: f32, color: vec4<f32>) -> vec4<f32> {
-    return mix(color, FOG_COLOR, ffog_calc_factor(d, fog_min, 32.0));
-}
\ No newline at end of file
+fn terrain_fog_apply(distance: f32, color: vec4<f32>) -> vec4<f32> {
+    let factor = terrain_fog_factor(distance) * terrain_fog.color.a;
+    return vec4<f32>(mix(color.rgb, terrain_fog.color.rgb, factor), color.a);
+}
@@ -19,6 +19,7 @@
 struct Vertex {
     @location(0) position: vec3<f32>,
     @location(1) voxel_data: u32,
+    @location(2) light: vec2<f32>,
 };
 
 struct VertexOutput {
@@ -26,8 +27,13 @@ struct VertexOutput {
     @location(0) voxel_normal: vec3<f32>,
     @location(1) voxel_data: u32,
     @location(2) world_position: vec3<f32>,
+    @location(3) ambient_occlusion: f32,
+    @location(4) light: vec2<f32>,
 };
 
+// Share of the light left in fully occluded voxel corners.
+const AMBIENT_OCCLUSION_MIN_LIGHT: f32 = 0.45;
+
 @vertex
 fn vertex(vertex: Vertex) -> VertexOutput {
     let world_position = mesh_position_local_to_world(mesh.model, vec4<f32>(vertex.position, 1.0));
@@ -37,6 +43,8 @@ fn vertex(vertex: Vertex) -> VertexOutput {
     out.voxel_normal = voxel_data_extract_normal(vertex.voxel_data);
     out.voxel_data = vertex.voxel_data;
     out.world_position = world_position.xyz;
+    out.ambient_occlusion = voxel_data_extract_ambient_occlusion(vertex.voxel_data);
+    out.light = vertex.light;
 
     return out;
 }
@@ -50,11 +58,25 @@ struct Fragment {
     @location(1) voxel_data: u32,
     /// The world position of the voxel vertex.
     @location(2) world_position: vec3<f32>,
+    /// The ambient occlusion interpolated between the corners of the voxel face.
+    @location(3) ambient_occlusion: f32,
+    /// The sky light and the block light reaching the voxel face, between zero and one.
+    @location(4) light: vec2<f32>,
 };
 
+// Share of the light left in the voxels the light doesn't reach.
+const VOXEL_LIGHT_MIN: f32 = 0.02;
+
+// Returns how much of the light is left at the specified voxel light level, each level dimming the light by a fifth.
+fn voxel_light_factor(light: vec2<f32>) -> f32 {
+    let level = max(light.x, light.y);
+    return max(pow(0.8, (1.0 - level) * 15.0), VOXEL_LIGHT_MIN);
+}
+
 fn prepare_pbr_input_from_voxel_mat(voxel_mat: VoxelMat, frag: Fragment) -> PbrInput {
     var base_color: vec4<f32> = voxel_mat.base_color;
     base_color = base_color + hash(vec4<f32>(floor(frag.world_position - frag.voxel_normal * 0.5), 1.0)) * 0.0226;
+    base_color = vec4<f32>(base_color.rgb * mix(AMBIENT_OCCLUSION_MIN_LIGHT, 1.0, frag.ambient_occlusion), base_color.a);
 
     var pbr_input: PbrInput = pbr_input_new();
     pbr_input.material.metallic = voxel_mat.metallic;
@@ -71,6 +93,9 @@ fn prepare_pbr_input_from_voxel_mat(voxel_mat: VoxelMat, frag: Fragment) -> PbrI
     pbr_input.N = normalize(mesh_normal_local_to_world(frag.voxel_normal));
     pbr_input.V = calculate_view(vec4<f32>(frag.world_position, 1.0), pbr_input.is_orthographic);
     pbr_input.flags = mesh.flags;
+#ifdef VOXEL_TRANSLUCENT
+    pbr_input.material.flags = STANDARD_MATERIAL_FLAGS_ALPHA_MODE_BLEND;
+#endif
     return pbr_input;
 }
 
@@ -80,11 +105,12 @@ fn fragment(frag: Fragment) -> @location(0) vec4<f32> {
 
     /// PBR lighting input data preparation
     var pbr_input = prepare_pbr_input_from_voxel_mat(material, frag);
-    let pbr_colour = tone_mapping(pbr(pbr_input));
-
-    // @todo: switch to bevy_pbr::fog
+    var pbr_colour = pbr(pbr_input);
+    pbr_colour = vec4<f32>(pbr_colour.rgb * voxel_light_factor(frag.light), pbr_colour.a);
 
-    //fragment distance from camera, used to determine amount of fog to apply.
+    // the fog is applied before the tone mapping, like bevy does for the other meshes.
     let fog_distance = distance(frag.world_position, view.world_position);
-    return ffog_apply_fog(fog_distance, f32(render_distance) * f32(TERRAIN_CHUNK_LENGTH), f32(TERRAIN_CHUNK_LENGTH), pbr_colour);
+    pbr_colour = terrain_fog_apply(fog_distance, pbr_colour);
+
+    return tone_mapping(pbr_colour);
 }
\ No newline at end of file
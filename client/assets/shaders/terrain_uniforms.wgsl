@@ -10,9 +10,18 @@ struct VoxelMat {
     reflectance: f32,
 };
 
+// The distance fog of the terrain, see `shaders/fog.wgsl`.
+struct TerrainFog {
+    color: vec4<f32>,
+    start: f32,
+    end: f32,
+    density: f32,
+    falloff: u32,
+};
+
 @group(1) @binding(0)
-var<uniform> render_distance: u32;
+var<uniform> terrain_fog: TerrainFog;
 
-// A GPU-suited representation of voxel materials.
+// A GPU-suited representation of voxel materials, indexed by material id.
 @group(1) @binding(1)
-var<uniform> voxel_materials: array<VoxelMat, 256>;
\ No newline at end of file
+var<storage, read> voxel_materials: array<VoxelMat>;
\ No newline at end of file
@@ -4,15 +4,14 @@
 // Layout of voxel information encoded into a single u32
 //
 //  00000000    00000000    00000000    00000000    
-//  XXXXXYYY    YYZZZZZ          NNN    MATERIAL
+//  STATE       ___AANNN    MATERIAL    MATERIAL
 //
-// X: X position
-// Y: Y position
-// Z: Z position
+// STATE: material specific voxel state (orientation, liquid level...)
+// A: ambient occlusion of the vertex, from 0 (fully occluded) to 3 (unoccluded)
 // N: normal index in the VOXEL_NORMALS array
 // MATERIAL: material index in the palette
 // 
-// The remaining 5 free bits could be used to store UV data or additional info or even extend voxel material id size.
+// The remaining 3 free bits could be used to store UV data or additional info.
 
 // An array of voxel face normals 
 var<private> VOXEL_NORMALS: array<vec3<f32>, 6> = array<vec3<f32>, 6>(
@@ -26,18 +25,20 @@ var<private> VOXEL_NORMALS: array<vec3<f32>, 6> = array<vec3<f32>, 6>(
 
 // Extracts the normal face index from the encoded voxel data
 fn voxel_data_extract_normal(voxel_data: u32) -> vec3<f32> {
-    return VOXEL_NORMALS[voxel_data >> 8u & 7u];
+    return VOXEL_NORMALS[voxel_data >> 16u & 7u];
 }
 
-// fn voxel_data_extract_position(voxel_data: u32) -> vec3<f32> {
-//     return vec3<f32>(
-//         f32(voxel_data >> 27u),
-//         f32(voxel_data >> 22u & 31u),
-//         f32(voxel_data >> 17u & 31u)
-//     );
-// }
+// Extracts the ambient occlusion of the vertex from the encoded voxel data, 1.0 being unoccluded
+fn voxel_data_extract_ambient_occlusion(voxel_data: u32) -> f32 {
+    return f32(voxel_data >> 19u & 3u) / 3.0;
+}
 
 // Extracts the material index from the encoded voxel data
 fn voxel_data_extract_material_index(voxel_data: u32) -> u32 {
-    return voxel_data & 255u;
+    return voxel_data & 65535u;
+}
+
+// Extracts the material specific voxel state from the encoded voxel data
+fn voxel_data_extract_state(voxel_data: u32) -> u32 {
+    return voxel_data >> 24u;
 }
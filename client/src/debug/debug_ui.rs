@@ -2,8 +2,8 @@ use bevy::{
     diagnostic::{Diagnostics, EntityCountDiagnosticsPlugin, FrameTimeDiagnosticsPlugin},
     input::{keyboard::KeyboardInput, ButtonState},
     prelude::{
-        Color, CoreSet, EventReader, IntoSystemConfig, IntoSystemConfigs, IntoSystemSetConfigs,
-        KeyCode, Plugin, Res, ResMut, Resource, SystemSet,
+        info, warn, Color, CoreSet, EventReader, EventWriter, IntoSystemConfig, IntoSystemConfigs,
+        IntoSystemSetConfigs, KeyCode, Plugin, Res, ResMut, Resource, SystemSet,
     },
 };
 use bevy_egui::{
@@ -12,8 +12,10 @@ use bevy_egui::{
 };
 
 use crate::voxel::{
-    material::VoxelMaterialRegistry, ChunkCommandQueue, ChunkEntities, ChunkLoadRadius,
-    CurrentLocalPlayerChunk, DirtyChunks,
+    material::{export_material_definitions, ImportMaterialDefinitions, VoxelMaterialRegistry},
+    storage::ChunkMap,
+    ChunkCommandQueue, ChunkEntities, ChunkLoadRadius, ChunkShape, CurrentLocalPlayerChunk,
+    DirtyChunks, DistanceFog, DistanceFogFalloff, Voxel,
 };
 
 fn display_debug_stats(mut egui: EguiContexts, diagnostics: Res<Diagnostics>) {
@@ -37,6 +39,7 @@ fn display_debug_stats(mut egui: EguiContexts, diagnostics: Res<Diagnostics>) {
     });
 }
 
+#[allow(clippy::too_many_arguments)]
 fn display_chunk_stats(
     mut egui: EguiContexts,
     dirty_chunks: Res<DirtyChunks>,
@@ -44,6 +47,8 @@ fn display_chunk_stats(
     mut chunk_loading_radius: ResMut<ChunkLoadRadius>,
     mut chunk_command_queue: ResMut<ChunkCommandQueue>,
     loaded_chunks: Res<ChunkEntities>,
+    chunk_map: Res<ChunkMap<Voxel, ChunkShape>>,
+    mut fog: ResMut<DistanceFog>,
 ) {
     egui::Window::new("voxel world stuff").show(egui.ctx_mut(), |ui| {
         ui.heading("Chunks");
@@ -52,9 +57,30 @@ fn display_chunk_stats(
             dirty_chunks.num_dirty()
         ));
         ui.label(format!("Loaded chunk count: {}", loaded_chunks.len()));
+        ui.label(format!(
+            "Loaded voxel data: {:.02} MiB",
+            chunk_map.heap_size() as f32 / (1024.0 * 1024.0)
+        ));
         ui.separator();
         ui.label("Horizontal chunk loading radius");
         ui.add(Slider::new(&mut chunk_loading_radius.horizontal, 8..=32));
+
+        // only write the fog back when it changes, as it uploads the terrain materials again.
+        let mut falloff = fog.falloff;
+        egui::containers::ComboBox::from_label("Fog falloff")
+            .selected_text(format!("{falloff:?}"))
+            .show_ui(ui, |ui| {
+                for mode in [
+                    DistanceFogFalloff::Linear,
+                    DistanceFogFalloff::Exponential,
+                    DistanceFogFalloff::ExponentialSquared,
+                ] {
+                    ui.selectable_value(&mut falloff, mode, format!("{mode:?}"));
+                }
+            });
+        if falloff != fog.falloff {
+            fog.falloff = falloff;
+        }
         ui.separator();
 
         if ui.button("Clear loaded chunks").clicked() {
@@ -97,8 +123,23 @@ fn display_material_editor(
     mut egui: EguiContexts,
     mut ui_state: ResMut<DebugUIState>,
     mut materials: ResMut<VoxelMaterialRegistry>,
+    mut import_requests: EventWriter<ImportMaterialDefinitions>,
 ) {
     egui::Window::new("material editor").show(egui.ctx_mut(), |ui| {
+        ui.horizontal(|ui| {
+            if ui.button("Export materials").clicked() {
+                match export_material_definitions(&materials) {
+                    Ok(path) => info!("Exported the materials to {}", path.display()),
+                    Err(err) => warn!("Cannot export the materials: {err}"),
+                }
+            }
+
+            if ui.button("Import materials").clicked() {
+                import_requests.send(ImportMaterialDefinitions);
+            }
+        });
+        ui.separator();
+
         ui.heading("Select material");
         egui::containers::ComboBox::from_label("Material")
             .selected_text(
@@ -115,14 +156,18 @@ fn display_material_editor(
                     .for_each(|(mat_index, mat)| {
                         content.selectable_value(
                             &mut ui_state.selected_mat,
-                            mat_index as u8,
-                            mat.name,
+                            mat_index as u16,
+                            &mat.name,
                         );
                     })
             });
 
         ui.heading("Material properties");
 
+        if ui.button("Reset to defaults").clicked() {
+            materials.reset_material(ui_state.selected_mat);
+        }
+
         // base_color
         ui.label("Base color");
 
@@ -163,6 +208,8 @@ fn display_material_editor(
             egui::color_picker::Alpha::Opaque,
         );
         selected_mat.emissive = Color::from(editable_emissive.to_array());
+        ui.label("Hardness");
+        ui.add(Slider::new(&mut selected_mat.hardness, 0.0..=5.0f32));
     });
 }
 
@@ -207,5 +254,5 @@ struct DebugUIState {
     display_mat_debug: bool,
 
     // DD
-    pub selected_mat: u8,
+    pub selected_mat: u16,
 }
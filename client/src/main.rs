@@ -14,15 +14,23 @@ mod voxel;
 
 fn main() {
     let mut app = App::default();
-    app.add_plugins(DefaultPlugins.set(WindowPlugin {
-        primary_window: Some(Window {
-            title: "vx_bevy".into(),
-            mode: WindowMode::Windowed,
-            ..default()
-        }),
-        ..default()
-    }))
+    app.add_plugins(
+        DefaultPlugins
+            .set(WindowPlugin {
+                primary_window: Some(Window {
+                    title: "vx_bevy".into(),
+                    mode: WindowMode::Windowed,
+                    ..default()
+                }),
+                ..default()
+            })
+            .set(AssetPlugin {
+                watch_for_changes: hot_reload_assets(),
+                ..default()
+            }),
+    )
     .add_state::<GameState>()
+    .insert_resource(terrain_gen_mode())
     .insert_resource(AmbientLight {
         color: Color::WHITE,
         brightness: 1.0,
@@ -43,6 +51,22 @@ fn main() {
     .run();
 }
 
+/// Streams the terrain from the server instead of generating it locally when started with `--remote-terrain`.
+fn terrain_gen_mode() -> voxel::TerrainGenMode {
+    if std::env::args().any(|arg| arg == "--remote-terrain") {
+        voxel::TerrainGenMode::Remote
+    } else {
+        voxel::TerrainGenMode::Local
+    }
+}
+
+/// Hot reloads the assets, such as the biome definitions, when started with `--hot-reload`.
+/// This is a debugging aid: the server never reloads its biome definitions,
+/// so the terrain generated afterwards no longer matches the terrain of the server.
+fn hot_reload_assets() -> bool {
+    std::env::args().any(|arg| arg == "--hot-reload")
+}
+
 #[derive(Clone, Eq, PartialEq, Debug, Hash, Default, States)]
 pub enum GameState {
     #[default]
@@ -1,11 +1,56 @@
 use super::{Body, CameraMode, Head};
-use crate::{debug::DebugUISet, voxel::networking::ControlledPlayer, GameState};
+use crate::{
+    debug::DebugUISet,
+    voxel::{
+        material::{VoxelMaterialFlags, VoxelMaterialRegistry},
+        networking::ControlledPlayer,
+        storage::ChunkMap,
+        ChunkMeshingSet, ChunkShape, DirtyChunks, Voxel,
+    },
+    GameState,
+};
 use bevy::{input::mouse::MouseMotion, prelude::*, window::CursorGrabMode};
 use bevy_egui::EguiContexts;
+use common::VoxelEdit;
 use std::f32::consts::FRAC_PI_2;
 
 const BODY_ROTATION_SLERP: f32 = 0.5;
 const DEFAULT_CAMERA_SENS: f32 = 0.005;
+/// Maximum distance from the camera at which voxels can be edited, accounting for the third person camera offset.
+const VOXEL_EDIT_REACH: f32 = 12.0;
+/// Delay between two voxels broken while the player keeps holding the break button.
+const VOXEL_BREAK_COOLDOWN: f32 = 0.2;
+
+const MATERIAL_SELECT_KEYS: [KeyCode; 9] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+];
+
+/// The voxel placed by the player when using the right mouse button.
+#[derive(Resource)]
+pub struct SelectedVoxel(pub Voxel);
+
+impl Default for SelectedVoxel {
+    fn default() -> Self {
+        Self(Voxel::new(1))
+    }
+}
+
+/// How long the player has been breaking the voxel it targets, see [`MaterialRegistryInfo::hardness`].
+///
+/// [`MaterialRegistryInfo::hardness`]: crate::voxel::material::MaterialRegistryInfo::hardness
+#[derive(Resource, Default)]
+pub struct VoxelBreakProgress {
+    target: Option<IVec3>,
+    elapsed: f32,
+}
 
 fn handle_player_mouse_move(
     mut head: Query<&mut Transform, With<Head>>,
@@ -147,6 +192,118 @@ fn update_player_body_rotation(
     body_transform.rotation = body_transform.rotation.slerp(desired, BODY_ROTATION_SLERP);
 }
 
+fn handle_player_select_voxel(
+    keys: Res<Input<KeyCode>>,
+    materials: Res<VoxelMaterialRegistry>,
+    mut selected: ResMut<SelectedVoxel>,
+) {
+    for (index, key) in MATERIAL_SELECT_KEYS.iter().enumerate() {
+        let id = index as u16 + 1;
+        if keys.just_pressed(*key) && materials.get_by_id(id).is_some() {
+            selected.0 = Voxel::new(id);
+        }
+    }
+}
+
+/// Breaks the voxel targeted by the camera once the left button is held for as long as its hardness,
+/// and places the selected voxel against it on right click.
+#[allow(clippy::too_many_arguments)]
+fn handle_player_voxel_edit(
+    btns: Res<Input<MouseButton>>,
+    time: Res<Time>,
+    mut break_progress: ResMut<VoxelBreakProgress>,
+    windows: Query<&Window>,
+    camera: Query<&GlobalTransform, With<CameraMode>>,
+    player: Query<&Transform, With<ControlledPlayer>>,
+    selected: Res<SelectedVoxel>,
+    materials: Res<VoxelMaterialRegistry>,
+    mut chunks: ResMut<ChunkMap<Voxel, ChunkShape>>,
+    mut dirty_chunks: ResMut<DirtyChunks>,
+    mut voxel_edits: EventWriter<VoxelEdit>,
+) {
+    let (Ok(camera), Ok(player)) = (camera.get_single(), player.get_single()) else {
+        return;
+    };
+
+    let breaking = btns.pressed(MouseButton::Left);
+    let placing = btns.just_pressed(MouseButton::Right);
+    if !breaking {
+        *break_progress = VoxelBreakProgress::default();
+    }
+
+    if !(breaking || placing) || windows.single().cursor.grab_mode != CursorGrabMode::Locked {
+        return;
+    }
+
+    let is_liquid = |voxel: Voxel| {
+        materials
+            .get_by_id(voxel.id)
+            .is_some_and(|mat| mat.flags.contains(VoxelMaterialFlags::LIQUID))
+    };
+
+    let Some(hit) = chunks.raycast(
+        camera.translation(),
+        camera.forward(),
+        VOXEL_EDIT_REACH,
+        |voxel| voxel != Voxel::EMPTY_VOXEL && !is_liquid(voxel),
+    ) else {
+        return;
+    };
+
+    let (target, voxel) = if breaking {
+        let Some(mat) = chunks
+            .voxel_at(hit.position)
+            .and_then(|voxel| materials.get_by_id(voxel.id))
+            .filter(|mat| !mat.flags.contains(VoxelMaterialFlags::UNBREAKABLE))
+        else {
+            return;
+        };
+
+        // start over when the player looks at another voxel, without skipping the cooldown.
+        if break_progress.target != Some(hit.position) {
+            break_progress.target = Some(hit.position);
+            break_progress.elapsed = break_progress.elapsed.min(0.0);
+        }
+
+        break_progress.elapsed += time.delta_seconds();
+        if break_progress.elapsed < mat.hardness {
+            return;
+        }
+
+        *break_progress = VoxelBreakProgress {
+            target: None,
+            elapsed: -VOXEL_BREAK_COOLDOWN,
+        };
+        (hit.position, Voxel::EMPTY_VOXEL)
+    } else {
+        let target = hit.position + hit.normal;
+
+        // don't let the player place a voxel inside its own body.
+        let feet = player.translation.floor().as_ivec3();
+        if hit.normal == IVec3::ZERO || target == feet || target == feet + IVec3::Y {
+            return;
+        }
+
+        (target, selected.0)
+    };
+
+    let Some(mut current) = chunks.voxel_at_mut(target) else {
+        return;
+    };
+
+    if !(*current == Voxel::EMPTY_VOXEL || is_liquid(*current) || voxel == Voxel::EMPTY_VOXEL) {
+        return;
+    }
+    *current = voxel;
+    drop(current);
+
+    dirty_chunks.mark_voxel_dirty(target);
+    voxel_edits.send(VoxelEdit {
+        position: target,
+        voxel,
+    });
+}
+
 #[derive(Hash, Copy, Clone, PartialEq, Eq, Debug, SystemSet)]
 /// Systems related to player controls.
 pub struct PlayerControllerSet;
@@ -165,6 +322,15 @@ impl Plugin for PlayerControllerPlugin {
                 .chain()
                 .in_set(OnUpdate(GameState::Game))
                 .after(DebugUISet::Display),
+        )
+        .init_resource::<SelectedVoxel>()
+        .init_resource::<VoxelBreakProgress>()
+        .add_systems(
+            (handle_player_select_voxel, handle_player_voxel_edit)
+                .chain()
+                .in_set(OnUpdate(GameState::Game))
+                .after(DebugUISet::Display)
+                .before(ChunkMeshingSet),
         );
     }
 }
@@ -1,5 +1,6 @@
 use bevy::{prelude::*, time, utils::HashMap};
 use bevy_rapier3d::prelude::{ActiveEvents, Collider, LockedAxes, RigidBody};
+use common::time_of_day::TimeOfDay;
 use rand::Rng;
 
 use crate::voxel::{
@@ -16,6 +17,7 @@ pub fn spawn_mobs(
     my_assets: Res<MyAssets>,
     mut query: Query<(&Transform, &mut MobSpawnTimer), With<ControlledPlayer>>,
     time: Res<time::Time>,
+    time_of_day: Res<TimeOfDay>,
 ) {
     // random number from 100 to 200
     let mut rng = rand::thread_rng();
@@ -31,7 +33,9 @@ pub fn spawn_mobs(
 
     if let Ok((transform, mut timer)) = query.get_single_mut() {
         if timer.current_mobs < timer.max_mobs {
-            timer.get_timer.tick(time.delta());
+            // mobs spawn twice as often at night.
+            let night_factor = if time_of_day.is_night() { 2 } else { 1 };
+            timer.get_timer.tick(time.delta() * night_factor);
             if timer.get_timer.just_finished() {
                 let player_pos = transform.translation;
                 let mob_pos = Vec3::new(
@@ -1,4 +1,4 @@
-use crate::GameState;
+use crate::{voxel::material::VoxelMaterialDefinitions, GameState};
 use bevy::prelude::*;
 use bevy_asset_loader::prelude::*;
 
@@ -40,4 +40,7 @@ pub struct MyAssets {
     // Arrow
     #[asset(path = "models/item/arrowx10North.gltf#Scene0")]
     pub arrow: Handle<Scene>,
+    // Voxel materials, the world is only meshed once they are known.
+    #[asset(path = "voxel.materials.ron")]
+    pub materials: Handle<VoxelMaterialDefinitions>,
 }
@@ -1,60 +1,37 @@
 use bevy::{
-    prelude::{info, Color, Plugin, Resource},
-    utils::HashMap,
+    asset::{AssetLoader, FileAssetIo, LoadContext, LoadedAsset},
+    prelude::{
+        info, AddAsset, AssetEvent, AssetServer, Assets, Color, Commands, DetectChanges,
+        EventReader, Handle, Plugin, Res, ResMut, Resource,
+    },
+    reflect::TypeUuid,
+    utils::{BoxedFuture, HashMap, HashSet},
 };
-use bitflags::bitflags;
-use std::{any::type_name, any::TypeId};
+use common::voxel::{
+    material::{VoxelMaterial, MATERIAL_DEFINITIONS_PATH},
+    materials::voxel_by_name,
+};
+
+use super::loading::MyAssets;
+use serde::{Deserialize, Serialize};
+use std::{any::type_name, any::TypeId, path::PathBuf};
 
-use super::Voxel;
+pub use common::voxel::material::VoxelMaterialFlags;
 
 //todo: rewrite this in a way which allows constifying stuff.
 
 // Registry info about a voxel material
-#[derive(Default)]
+#[derive(Default, Clone, Debug, PartialEq)]
 pub struct MaterialRegistryInfo {
-    pub name: &'static str,
+    pub name: String,
     pub base_color: Color,
     pub flags: VoxelMaterialFlags,
     pub emissive: Color,
     pub perceptual_roughness: f32,
     pub metallic: f32,
     pub reflectance: f32,
-}
-
-/// Helper / marker trait for voxel materials.
-pub trait VoxelMaterial {
-    const ID: u8;
-
-    fn into_voxel() -> Voxel {
-        Voxel(Self::ID)
-    }
-}
-
-#[macro_export]
-macro_rules! voxel_material {
-    ($types: ident, $id: expr) => {
-        pub struct $types;
-        impl $types {
-            pub const NAME: &'static str = stringify!($types);
-        }
-        impl $crate::voxel::material::VoxelMaterial for $types {
-            const ID: u8 = $id;
-        }
-    };
-}
-
-bitflags! {
-    pub struct VoxelMaterialFlags : u32 {
-        const SOLID = 0;
-        const LIQUID = 1 << 1;
-        const UNBREAKABLE = 1 << 2;
-    }
-}
-
-impl Default for VoxelMaterialFlags {
-    fn default() -> Self {
-        Self::SOLID
-    }
+    /// Seconds the player has to hold the break button for the material to break, zero breaks it instantly.
+    pub hardness: f32,
 }
 
 /// A registry for voxel material types.
@@ -62,38 +39,71 @@ impl Default for VoxelMaterialFlags {
 #[derive(Resource)]
 pub struct VoxelMaterialRegistry {
     materials: Vec<MaterialRegistryInfo>,
-    mat_ids: HashMap<TypeId, usize>,
+    mat_ids: HashMap<TypeId, u16>,
+    /// The materials as shipped with the game, which edited materials can be reset to.
+    defaults: HashMap<u16, MaterialRegistryInfo>,
 }
 
 #[allow(dead_code)]
 impl VoxelMaterialRegistry {
     #[inline]
-    pub fn get_by_id(&self, id: u8) -> Option<&MaterialRegistryInfo> {
+    pub fn get_by_id(&self, id: u16) -> Option<&MaterialRegistryInfo> {
         self.materials.get(id as usize)
     }
 
-    pub fn get_mut_by_id(&mut self, id: u8) -> Option<&mut MaterialRegistryInfo> {
+    pub fn get_mut_by_id(&mut self, id: u16) -> Option<&mut MaterialRegistryInfo> {
         self.materials.get_mut(id as usize)
     }
 
     pub fn get_by_type<M: 'static>(&self) -> Option<&MaterialRegistryInfo> {
         self.mat_ids
             .get(&TypeId::of::<M>())
-            .map(|x| self.materials.get(*x).unwrap())
+            .and_then(|id| self.get_by_id(*id))
     }
 
-    pub fn get_id_for_type<M: 'static>(&self) -> Option<u8> {
-        self.mat_ids.get(&TypeId::of::<M>()).map(|x| *x as u8)
+    pub fn get_id_for_type<M: 'static>(&self) -> Option<u16> {
+        self.mat_ids.get(&TypeId::of::<M>()).copied()
     }
 
-    pub fn register_material<M: 'static>(&mut self, mat: MaterialRegistryInfo) {
-        self.materials.push(mat);
-        info!(
-            "Registered material {:?} (ID: {})",
-            type_name::<M>(),
-            self.materials.len() - 1
-        );
-        self.mat_ids.insert(TypeId::of::<M>(), self.materials.len());
+    /// Returns the id of the material with the specified name.
+    pub fn get_id_by_name(&self, name: &str) -> Option<u16> {
+        self.materials
+            .iter()
+            .position(|mat| mat.name == name)
+            .map(|id| id as u16)
+    }
+
+    /// Registers a material defined by code under its [`VoxelMaterial::ID`].
+    pub fn register_material<M: VoxelMaterial + 'static>(&mut self, mat: MaterialRegistryInfo) {
+        info!("Registered material {:?} (ID: {})", type_name::<M>(), M::ID);
+        self.defaults.insert(M::ID, mat.clone());
+        self.set_material(M::ID, mat);
+        self.mat_ids.insert(TypeId::of::<M>(), M::ID);
+    }
+
+    /// Registers a material loaded from the shipped definition file, which becomes its default.
+    fn load_material(&mut self, id: u16, mat: MaterialRegistryInfo) {
+        self.defaults.insert(id, mat.clone());
+        self.set_material(id, mat);
+    }
+
+    /// Resets a material to the properties it is shipped with, returns `false` if it has no defaults.
+    pub fn reset_material(&mut self, id: u16) -> bool {
+        let Some(default) = self.defaults.get(&id).cloned() else {
+            return false;
+        };
+
+        self.set_material(id, default);
+        true
+    }
+
+    /// Registers or replaces the material with the specified id.
+    pub fn set_material(&mut self, id: u16, mat: MaterialRegistryInfo) {
+        let index = id as usize;
+        if index >= self.materials.len() {
+            self.materials.resize_with(index + 1, Default::default);
+        }
+        self.materials[index] = mat;
     }
 
     pub fn iter_mats(&self) -> impl Iterator<Item = &MaterialRegistryInfo> {
@@ -106,11 +116,12 @@ impl Default for VoxelMaterialRegistry {
         let mut registry = Self {
             materials: Default::default(),
             mat_ids: Default::default(),
+            defaults: Default::default(),
         };
 
         registry.register_material::<Void>(MaterialRegistryInfo {
             base_color: Color::BLACK,
-            name: "Void",
+            name: "Void".into(),
             flags: VoxelMaterialFlags::SOLID,
             ..Default::default()
         });
@@ -122,9 +133,259 @@ impl Default for VoxelMaterialRegistry {
 // The material with ID #0;
 pub struct Void;
 
+impl VoxelMaterial for Void {
+    const ID: u16 = 0;
+}
+
+/// A material as written in a `.materials.ron` file.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct MaterialDefinition {
+    /// The stable name of the material, which the id of the materials known by the game is looked up from.
+    pub name: String,
+    /// The id of a material unknown to the game, it must not change once worlds are saved with it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<u16>,
+    pub base_color: Color,
+    pub flags: VoxelMaterialFlags,
+    pub emissive: Color,
+    pub perceptual_roughness: f32,
+    pub metallic: f32,
+    pub reflectance: f32,
+    pub hardness: f32,
+}
+
+impl Default for MaterialDefinition {
+    fn default() -> Self {
+        let info = MaterialRegistryInfo::default();
+        Self {
+            name: info.name,
+            id: None,
+            base_color: info.base_color,
+            flags: info.flags,
+            emissive: Color::BLACK,
+            perceptual_roughness: info.perceptual_roughness,
+            metallic: info.metallic,
+            reflectance: info.reflectance,
+            hardness: info.hardness,
+        }
+    }
+}
+
+impl MaterialDefinition {
+    /// Looks up the id of the material, so reordering the definitions never changes the ids stored in saved worlds.
+    fn resolve_id(&self) -> Result<u16, String> {
+        let known_id = voxel_by_name(&self.name).map(|voxel| voxel.id);
+
+        match (known_id, self.id) {
+            (Some(known), Some(id)) if known != id => Err(format!(
+                "material `{}` has id {id} but the game knows it as {known}",
+                self.name
+            )),
+            (Some(id), _) | (None, Some(id)) if id != Void::ID => Ok(id),
+            (None, None) => Err(format!(
+                "material `{}` is unknown to the game and needs an id",
+                self.name
+            )),
+            _ => Err(format!(
+                "material `{}` cannot use the id {} reserved for empty voxels",
+                self.name,
+                Void::ID
+            )),
+        }
+    }
+
+    fn from_registry_info(id: u16, mat: &MaterialRegistryInfo) -> Self {
+        Self {
+            name: mat.name.clone(),
+            // the ids of the materials known by the game are looked up from their names.
+            id: voxel_by_name(&mat.name).is_none().then_some(id),
+            base_color: mat.base_color,
+            flags: mat.flags,
+            emissive: mat.emissive,
+            perceptual_roughness: mat.perceptual_roughness,
+            metallic: mat.metallic,
+            reflectance: mat.reflectance,
+            hardness: mat.hardness,
+        }
+    }
+
+    fn into_registry_info(self) -> MaterialRegistryInfo {
+        MaterialRegistryInfo {
+            name: self.name,
+            base_color: self.base_color,
+            flags: self.flags,
+            emissive: self.emissive,
+            perceptual_roughness: self.perceptual_roughness,
+            metallic: self.metallic,
+            reflectance: self.reflectance,
+            hardness: self.hardness,
+        }
+    }
+}
+
+/// The materials defined in a `.materials.ron` asset file, along their ids.
+#[derive(TypeUuid, Debug)]
+#[uuid = "b5b0d8f2-5e43-4c1b-8a7e-2f4d3c6a9e51"]
+pub struct VoxelMaterialDefinitions(Vec<(u16, MaterialRegistryInfo)>);
+
+/// Loads the `.materials.ron` material definition files.
+#[derive(Default)]
+pub struct VoxelMaterialDefinitionsLoader;
+
+impl AssetLoader for VoxelMaterialDefinitionsLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let definitions = ron::de::from_bytes::<Vec<MaterialDefinition>>(bytes)?;
+            let mut ids = HashSet::new();
+            let mut materials = Vec::with_capacity(definitions.len());
+
+            for definition in definitions {
+                let id = definition.resolve_id().map_err(bevy::asset::Error::msg)?;
+                if !ids.insert(id) {
+                    return Err(bevy::asset::Error::msg(format!(
+                        "several materials use the id {id}"
+                    )));
+                }
+                materials.push((id, definition.into_registry_info()));
+            }
+
+            load_context.set_default_asset(LoadedAsset::new(VoxelMaterialDefinitions(materials)));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["materials.ron"]
+    }
+}
+
+/// The asset file the materials edited in game are exported to, loaded on top of the shipped definitions.
+const USER_MATERIAL_DEFINITIONS_PATH: &str = "voxel.user.materials.ron";
+
+/// The asset folder the material definitions are exported to, matching the default [`bevy::asset::AssetPlugin`] settings.
+const ASSET_FOLDER: &str = "assets";
+
+/// The user material definitions file, the shipped one being loaded along the other assets of the game, see [`MyAssets`].
+#[derive(Resource)]
+struct VoxelMaterialDefinitionsHandles {
+    /// Missing until edited materials are exported.
+    user: Option<Handle<VoxelMaterialDefinitions>>,
+}
+
+/// Sent to load the material definition files again, discarding the changes made since they were last loaded or exported.
+pub struct ImportMaterialDefinitions;
+
+fn user_material_definitions_file() -> PathBuf {
+    FileAssetIo::get_base_path()
+        .join(ASSET_FOLDER)
+        .join(USER_MATERIAL_DEFINITIONS_PATH)
+}
+
+/// Writes the materials which differ from the shipped ones to the user material definitions file, returning the path written to.
+/// The file is loaded back at startup, or when the materials are imported.
+pub fn export_material_definitions(registry: &VoxelMaterialRegistry) -> Result<PathBuf, String> {
+    let definitions: Vec<MaterialDefinition> = registry
+        .iter_mats()
+        .enumerate()
+        .skip(1)
+        .filter(|(id, mat)| {
+            !mat.name.is_empty() && registry.defaults.get(&(*id as u16)) != Some(*mat)
+        })
+        .map(|(id, mat)| MaterialDefinition::from_registry_info(id as u16, mat))
+        .collect();
+
+    let path = user_material_definitions_file();
+    let contents = ron::ser::to_string_pretty(&definitions, Default::default())
+        .map_err(|err| err.to_string())?;
+    std::fs::write(&path, contents).map_err(|err| err.to_string())?;
+
+    Ok(path)
+}
+
+fn load_material_definitions(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(VoxelMaterialDefinitionsHandles {
+        user: user_material_definitions_file()
+            .exists()
+            .then(|| asset_server.load(USER_MATERIAL_DEFINITIONS_PATH)),
+    });
+}
+
+/// Loads the material definition files again when requested, along the user file exported since startup.
+fn import_material_definitions(
+    mut requests: EventReader<ImportMaterialDefinitions>,
+    asset_server: Res<AssetServer>,
+    mut handles: ResMut<VoxelMaterialDefinitionsHandles>,
+) {
+    if requests.iter().count() == 0 {
+        return;
+    }
+
+    asset_server.reload_asset(MATERIAL_DEFINITIONS_PATH);
+    match handles.user {
+        Some(_) => asset_server.reload_asset(USER_MATERIAL_DEFINITIONS_PATH),
+        None if user_material_definitions_file().exists() => {
+            handles.user = Some(asset_server.load(USER_MATERIAL_DEFINITIONS_PATH));
+        }
+        None => {}
+    }
+}
+
+/// Registers the materials of the shipped definition file then the ones of the user file, once loaded and each time one of them changes.
+/// The shipped file is the only definition of the materials, so nothing is registered before the game assets are loaded.
+fn apply_material_definitions(
+    mut events: EventReader<AssetEvent<VoxelMaterialDefinitions>>,
+    definitions: Res<Assets<VoxelMaterialDefinitions>>,
+    handles: Res<VoxelMaterialDefinitionsHandles>,
+    assets: Option<Res<MyAssets>>,
+    mut registry: ResMut<VoxelMaterialRegistry>,
+) {
+    let Some(assets) = assets else {
+        return;
+    };
+
+    let changed = events.iter().any(|event| {
+        matches!(
+            event,
+            AssetEvent::Created { .. } | AssetEvent::Modified { .. }
+        )
+    });
+    if !changed && !assets.is_added() {
+        return;
+    }
+
+    if let Some(shipped) = definitions.get(&assets.materials) {
+        for (id, mat) in shipped.0.iter() {
+            registry.load_material(*id, mat.clone());
+        }
+        info!("Loaded {} material definitions", shipped.0.len());
+    }
+
+    if let Some(user) = handles
+        .user
+        .as_ref()
+        .and_then(|handle| definitions.get(handle))
+    {
+        for (id, mat) in user.0.iter() {
+            registry.set_material(*id, mat.clone());
+        }
+        info!("Loaded {} edited material definitions", user.0.len());
+    }
+}
+
 pub struct VoxelMaterialPlugin;
 impl Plugin for VoxelMaterialPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
-        app.init_resource::<VoxelMaterialRegistry>();
+        app.init_resource::<VoxelMaterialRegistry>()
+            .add_asset::<VoxelMaterialDefinitions>()
+            .init_asset_loader::<VoxelMaterialDefinitionsLoader>()
+            .add_event::<ImportMaterialDefinitions>()
+            .add_startup_system(load_material_definitions)
+            .add_system(import_material_definitions)
+            .add_system(apply_material_definitions);
     }
 }
@@ -1,13 +1,9 @@
-/// Storage primitives for storing voxel data
-pub mod storage;
+pub use common::voxel::{storage, terraingen};
 
 /// Utils for managing a voxel world.
 mod world;
 pub use world::*;
 
-/// Terrain generator.
-pub mod terraingen;
-
 mod actor;
 pub use actor::*;
 
@@ -26,11 +22,6 @@ pub mod render;
 /// Systems for defining voxel materials with physical properties.
 pub mod material;
 
-/// rust ports of signed distance field functions for use in world generation.
-pub mod sdf;
-
 pub mod events;
 
-mod voxel;
-
-pub use voxel::*;
+pub use common::voxel::{MaterialVoxel, Voxel};
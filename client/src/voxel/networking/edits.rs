@@ -0,0 +1,105 @@
+use super::stream::PendingChunkPayloads;
+use crate::{
+    voxel::{
+        storage::ChunkMap, Chunk, ChunkMeshingSet, ChunkShape, DirtyChunks, TerrainGenMode,
+        TerrainGenTask, Voxel,
+    },
+    GameState,
+};
+use bevy::prelude::*;
+use bevy_renet::renet::RenetClient;
+use common::{
+    ChunkEditsRequest, ClientChannel, ServerChannel, VoxelEdit, VoxelEditBatch, VoxelFall,
+    VoxelFallBatch,
+};
+
+/// Sends the voxel edits made locally this frame to the server.
+fn send_voxel_edits(mut voxel_edits: EventReader<VoxelEdit>, mut client: ResMut<RenetClient>) {
+    let batch = VoxelEditBatch {
+        edits: voxel_edits.iter().copied().collect(),
+    };
+
+    if !batch.edits.is_empty() {
+        let message = bincode::serialize(&batch).unwrap();
+        client.send_message(ClientChannel::VoxelEdits, message);
+    }
+}
+
+/// Sends the voxels which landed this frame after falling by gravity to the server.
+fn send_voxel_falls(mut voxel_falls: EventReader<VoxelFall>, mut client: ResMut<RenetClient>) {
+    let batch = VoxelFallBatch {
+        falls: voxel_falls.iter().copied().collect(),
+    };
+
+    if !batch.falls.is_empty() {
+        let message = bincode::serialize(&batch).unwrap();
+        client.send_message(ClientChannel::VoxelFalls, message);
+    }
+}
+
+/// Asks the server for the edits made to the chunks which finished generating locally.
+/// Chunks streamed by the server already hold their edits, and never carry a [`TerrainGenTask`].
+fn request_chunk_edits(
+    mut loaded_chunks: RemovedComponents<TerrainGenTask>,
+    chunks: Query<&Chunk>,
+    mut client: ResMut<RenetClient>,
+) {
+    let request = ChunkEditsRequest {
+        chunks: loaded_chunks
+            .iter()
+            .filter_map(|entity| chunks.get(entity).ok())
+            .map(|chunk| chunk.0)
+            .collect(),
+    };
+
+    if !request.chunks.is_empty() {
+        let message = bincode::serialize(&request).unwrap();
+        client.send_message(ClientChannel::ChunkEdits, message);
+    }
+}
+
+/// Applies the voxel edits received from the server to the loaded chunks,
+/// including the voxels to restore where the server rejected a local edit.
+fn receive_voxel_edits(
+    mut client: ResMut<RenetClient>,
+    mut chunk_map: ResMut<ChunkMap<Voxel, ChunkShape>>,
+    mut dirty_chunks: ResMut<DirtyChunks>,
+    mut pending: ResMut<PendingChunkPayloads>,
+    terrain_gen_mode: Res<TerrainGenMode>,
+) {
+    while let Some(message) = client.receive_message(ServerChannel::VoxelEdits) {
+        let Ok(batch) = bincode::deserialize::<VoxelEditBatch>(&message) else {
+            warn!("Received a malformed voxel edit batch");
+            continue;
+        };
+
+        for edit in batch.edits {
+            if let Some(mut voxel) = chunk_map.voxel_at_mut(edit.position) {
+                *voxel = edit.voxel;
+                drop(voxel);
+                dirty_chunks.mark_voxel_dirty(edit.position);
+            } else if *terrain_gen_mode == TerrainGenMode::Remote {
+                // the streamed chunk may still be on its way, the edit is applied once it is received.
+                pending.apply_edit(edit);
+            }
+            // otherwise the edits are requested again once the chunk finished generating.
+        }
+    }
+}
+
+pub struct VoxelEditsSyncPlugin;
+impl Plugin for VoxelEditsSyncPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.add_event::<VoxelEdit>().add_systems(
+            (
+                send_voxel_edits,
+                send_voxel_falls,
+                request_chunk_edits.run_if(resource_equals(TerrainGenMode::Local)),
+                receive_voxel_edits,
+            )
+                .distributive_run_if(bevy_renet::transport::client_connected)
+                .in_set(OnUpdate(GameState::Game))
+                .before(ChunkMeshingSet),
+        );
+    }
+}
@@ -7,6 +7,8 @@ use bevy_renet::renet::{
 use common::{connection_config, PlayerCommand, PlayerInput, PROTOCOL_ID};
 use std::{net::UdpSocket, time::SystemTime};
 
+pub mod edits;
+pub mod stream;
 pub mod sync;
 
 pub struct NetworkingPlugin;
@@ -20,6 +22,8 @@ impl Plugin for NetworkingPlugin {
             .insert_resource(transport)
             .insert_resource(NetworkMapping::default())
             .add_plugin(sync::NetSyncPlugin)
+            .add_plugin(edits::VoxelEditsSyncPlugin)
+            .add_plugin(stream::ChunkStreamingPlugin)
             .add_system(panic_on_error_system.in_set(OnUpdate(GameState::Game)));
     }
 }
@@ -0,0 +1,149 @@
+use crate::{
+    voxel::{
+        storage::{decode_chunk, ChunkMap, PaletteBuffer},
+        ChunkEntities, ChunkLoadRadius, ChunkMeshingSet, ChunkShape, ChunkUnloaded,
+        CurrentLocalPlayerChunk, DirtyChunks, TerrainGenMode, Voxel, CHUNK_LENGTH,
+    },
+    GameState,
+};
+use bevy::{prelude::*, utils::HashMap};
+use bevy_renet::renet::RenetClient;
+use common::{
+    ChunkPayload, ChunkViewRadius, ChunksUnloaded, ClientChannel, ServerChannel, VoxelEdit,
+};
+
+/// Chunks received from the server before their chunk entity was created,
+/// along the edits received for chunks still on their way, as chunks and edits are sent on separate channels.
+#[derive(Default, Resource)]
+pub struct PendingChunkPayloads {
+    chunks: HashMap<IVec3, PaletteBuffer<Voxel, ChunkShape>>,
+    edits: HashMap<IVec3, Vec<VoxelEdit>>,
+}
+
+impl PendingChunkPayloads {
+    /// Applies an edit to a received chunk waiting for its entity,
+    /// or keeps it until its chunk is received otherwise.
+    pub fn apply_edit(&mut self, edit: VoxelEdit) {
+        let key = !IVec3::splat(CHUNK_LENGTH as i32 - 1) & edit.position;
+
+        match self.chunks.get_mut(&key) {
+            Some(buffer) => buffer.set_voxel((edit.position - key).as_uvec3(), edit.voxel),
+            None => self.edits.entry(key).or_default().push(edit),
+        }
+    }
+}
+
+fn streams_terrain(terrain_gen_mode: Res<TerrainGenMode>) -> bool {
+    *terrain_gen_mode == TerrainGenMode::Remote
+}
+
+/// Subscribes to the chunks around the player, and updates the subscription when the view distance changes.
+/// The debug UI marks the view distance as changed every frame it is displayed, so only actual changes are sent.
+fn send_chunk_view_radius(
+    view_radius: Res<ChunkLoadRadius>,
+    mut client: ResMut<RenetClient>,
+    mut last_sent: Local<Option<ChunkViewRadius>>,
+) {
+    let radius = ChunkViewRadius {
+        horizontal: view_radius.horizontal,
+        vertical: view_radius.vertical,
+    };
+
+    if *last_sent != Some(radius) {
+        let message = bincode::serialize(&radius).unwrap();
+        client.send_message(ClientChannel::ChunkView, message);
+        *last_sent = Some(radius);
+    }
+}
+
+/// Tells the server which streamed chunks were dropped, so it streams them again once they come back in view.
+fn send_unloaded_chunks(mut unloaded: EventReader<ChunkUnloaded>, mut client: ResMut<RenetClient>) {
+    let notice = ChunksUnloaded {
+        chunks: unloaded.iter().map(|chunk| chunk.0).collect(),
+    };
+
+    if !notice.chunks.is_empty() {
+        let message = bincode::serialize(&notice).unwrap();
+        client.send_message(ClientChannel::ChunkUnload, message);
+    }
+}
+
+/// Inserts the chunks streamed by the server into the voxel map.
+/// Clients generating their terrain themselves receive the chunks the server saved along their edits this way too.
+fn receive_chunk_payloads(
+    mut client: ResMut<RenetClient>,
+    mut pending: ResMut<PendingChunkPayloads>,
+    mut chunk_map: ResMut<ChunkMap<Voxel, ChunkShape>>,
+    mut dirty_chunks: ResMut<DirtyChunks>,
+    chunk_entities: Res<ChunkEntities>,
+    player_pos: Res<CurrentLocalPlayerChunk>,
+    view_radius: Res<ChunkLoadRadius>,
+) {
+    while let Some(message) = client.receive_message(ServerChannel::ChunkData) {
+        let Some((key, buffer)) = bincode::deserialize::<ChunkPayload>(&message)
+            .ok()
+            .and_then(|payload| Some((payload.key, decode_chunk(&payload.data)?)))
+        else {
+            warn!("Received a malformed chunk payload");
+            continue;
+        };
+
+        let mut buffer = PaletteBuffer::from(buffer);
+        for edit in pending.edits.remove(&key).into_iter().flatten() {
+            buffer.set_voxel((edit.position - key).as_uvec3(), edit.voxel);
+        }
+        pending.chunks.insert(key, buffer);
+    }
+
+    let ready: Vec<IVec3> = pending
+        .chunks
+        .keys()
+        .filter(|key| chunk_entities.entity(**key).is_some())
+        .copied()
+        .collect();
+
+    for key in ready {
+        if let Some(buffer) = pending.chunks.remove(&key) {
+            dirty_chunks.mark_loaded(key, &buffer);
+            chunk_map.insert(key, buffer);
+        }
+    }
+
+    // the server may stream a chunk slightly before the player reaches it, drop the ones left far behind.
+    let max_distance = (view_radius.horizontal.max(view_radius.vertical) + 1) * CHUNK_LENGTH as i32;
+    let mut dropped = ChunksUnloaded::default();
+    let in_reach = |key: &IVec3| (*key - player_pos.chunk_min).abs().max_element() <= max_distance;
+    pending.chunks.retain(|key, _| {
+        let keep = in_reach(key);
+        if !keep {
+            dropped.chunks.push(*key);
+        }
+        keep
+    });
+    pending.edits.retain(|key, _| in_reach(key));
+
+    if !dropped.chunks.is_empty() {
+        let message = bincode::serialize(&dropped).unwrap();
+        client.send_message(ClientChannel::ChunkUnload, message);
+    }
+}
+
+pub struct ChunkStreamingPlugin;
+impl Plugin for ChunkStreamingPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.init_resource::<PendingChunkPayloads>()
+            .add_systems(
+                (send_chunk_view_radius, send_unloaded_chunks)
+                    .distributive_run_if(bevy_renet::transport::client_connected)
+                    .distributive_run_if(streams_terrain)
+                    .in_set(OnUpdate(GameState::Game))
+                    .before(ChunkMeshingSet),
+            )
+            .add_system(
+                receive_chunk_payloads
+                    .run_if(bevy_renet::transport::client_connected)
+                    .in_set(OnUpdate(GameState::Game))
+                    .before(ChunkMeshingSet),
+            );
+    }
+}
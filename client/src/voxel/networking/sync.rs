@@ -106,6 +106,16 @@ fn sync_players(
                 lobby.players.insert(id, player_info);
                 network_mapping.0.insert(entity, client_entity.id());
             }
+            ServerMessages::SeaLevel { sea_level } => {
+                cmds.insert_resource(sea_level);
+            }
+            ServerMessages::WorldSeed { seed } => {
+                println!("World seed: {}", seed.0);
+                cmds.insert_resource(seed);
+            }
+            ServerMessages::TimeOfDay { time_of_day } => {
+                cmds.insert_resource(time_of_day);
+            }
             ServerMessages::PlayerRemove { id } => {
                 println!("Player {} disconnected.", id);
                 if let Some(PlayerInfo {
@@ -1,5 +1,6 @@
 use crate::voxel::material::VoxelMaterialRegistry;
 use bevy::{
+    pbr::MeshPipelineKey,
     prelude::*,
     reflect::TypeUuid,
     render::{
@@ -13,9 +14,17 @@ use bevy::{
 /// A marker component for voxel meshes.
 pub struct VoxelTerrainMesh;
 
+#[derive(Component, Clone, Copy, Default)]
+/// A marker component for the voxel meshes drawn in the translucent pass.
+pub struct TranslucentVoxelTerrainMesh;
+
 impl VoxelTerrainMesh {
     pub const ATTRIBUTE_DATA: MeshVertexAttribute =
         MeshVertexAttribute::new("Vertex_Data", 0x696969, VertexFormat::Uint32);
+
+    /// The sky light and block light of the vertex, between zero and one.
+    pub const ATTRIBUTE_LIGHT: MeshVertexAttribute =
+        MeshVertexAttribute::new("Vertex_Light", 0x69696a, VertexFormat::Float32x2);
 }
 
 #[derive(ShaderType, Clone, Copy, Default)]
@@ -28,20 +37,54 @@ pub struct GpuVoxelMaterial {
     reflectance: f32,
 }
 
-#[derive(AsBindGroup, ShaderType, Clone, TypeUuid)]
+/// The distance fog applied to the terrain, using the same falloff formulas as [`bevy::pbr::FogFalloff`].
+#[derive(ShaderType, Clone, Copy, Debug)]
+pub struct GpuDistanceFog {
+    pub color: Color,
+    /// The distances the linear fog starts and ends at.
+    pub start: f32,
+    pub end: f32,
+    /// The density of the exponential fogs.
+    pub density: f32,
+    /// One of the `GpuDistanceFog::FALLOFF_*` modes.
+    pub falloff: u32,
+}
+
+impl GpuDistanceFog {
+    pub const FALLOFF_LINEAR: u32 = 0;
+    pub const FALLOFF_EXPONENTIAL: u32 = 1;
+    pub const FALLOFF_EXPONENTIAL_SQUARED: u32 = 2;
+}
+
+impl Default for GpuDistanceFog {
+    fn default() -> Self {
+        Self {
+            color: Color::rgb(0.4, 0.4, 0.4),
+            start: 368.0,
+            end: 512.0,
+            density: 0.0,
+            falloff: Self::FALLOFF_LINEAR,
+        }
+    }
+}
+
+#[derive(AsBindGroup, Clone, TypeUuid)]
 #[uuid = "1e31e29e-73d8-419c-8293-876ae81d2636"]
 pub struct GpuTerrainUniforms {
     #[uniform(0)]
-    pub render_distance: u32,
-    #[uniform(1)]
-    pub materials: [GpuVoxelMaterial; 256],
+    pub fog: GpuDistanceFog,
+    /// The voxel materials indexed by id, as many as are registered.
+    #[storage(1, read_only)]
+    pub materials: Vec<GpuVoxelMaterial>,
+    pub alpha_mode: AlphaMode,
 }
 
 impl Default for GpuTerrainUniforms {
     fn default() -> Self {
         Self {
-            render_distance: 16,
-            materials: [default(); 256],
+            fog: default(),
+            materials: vec![default()],
+            alpha_mode: AlphaMode::Opaque,
         }
     }
 }
@@ -55,15 +98,30 @@ impl Material for GpuTerrainUniforms {
         "shaders/terrain_pipeline.wgsl".into()
     }
 
+    fn alpha_mode(&self) -> AlphaMode {
+        self.alpha_mode
+    }
+
     fn specialize(
         _pipeline: &bevy::pbr::MaterialPipeline<Self>,
         descriptor: &mut bevy::render::render_resource::RenderPipelineDescriptor,
         layout: &bevy::render::mesh::MeshVertexBufferLayout,
-        _key: bevy::pbr::MaterialPipelineKey<Self>,
+        key: bevy::pbr::MaterialPipelineKey<Self>,
     ) -> Result<(), bevy::render::render_resource::SpecializedMeshPipelineError> {
+        if key
+            .mesh_key
+            .intersection(MeshPipelineKey::BLEND_RESERVED_BITS)
+            == MeshPipelineKey::BLEND_ALPHA
+        {
+            if let Some(fragment) = descriptor.fragment.as_mut() {
+                fragment.shader_defs.push("VOXEL_TRANSLUCENT".into());
+            }
+        }
+
         let vertex_layout = layout.get_layout(&[
             Mesh::ATTRIBUTE_POSITION.at_shader_location(0),
             VoxelTerrainMesh::ATTRIBUTE_DATA.at_shader_location(1),
+            VoxelTerrainMesh::ATTRIBUTE_LIGHT.at_shader_location(2),
         ])?;
         descriptor.vertex.buffers = vec![vertex_layout];
         Ok(())
@@ -75,46 +133,76 @@ fn update_chunk_material_singleton(
     mut materials: ResMut<Assets<GpuTerrainUniforms>>,
     chunk_material: ResMut<ChunkMaterialSingleton>,
     voxel_materials: Res<VoxelMaterialRegistry>,
-    mut chunk_entities: Query<(Entity, &mut Handle<GpuTerrainUniforms>)>,
+    mut chunk_entities: Query<(
+        &mut Handle<GpuTerrainUniforms>,
+        Option<&TranslucentVoxelTerrainMesh>,
+    )>,
 ) {
     if chunk_material.is_changed() {
-        let mut gpu_mats = GpuTerrainUniforms {
-            materials: [GpuVoxelMaterial {
-                base_color: Color::WHITE,
-                flags: 0,
-                ..Default::default()
-            }; 256],
-            render_distance: 32,
+        // the fog is kept up to date separately, see `DistanceFog`.
+        let fog = materials
+            .get(&chunk_material.opaque)
+            .map_or_else(default, |mat| mat.fog);
+        let opaque = GpuTerrainUniforms {
+            materials: voxel_materials
+                .iter_mats()
+                .map(|material| GpuVoxelMaterial {
+                    base_color: material.base_color,
+                    flags: material.flags.bits(),
+                    emissive: material.emissive,
+                    perceptual_roughness: material.perceptual_roughness,
+                    metallic: material.metallic,
+                    reflectance: material.reflectance,
+                })
+                .collect(),
+            fog,
+            alpha_mode: AlphaMode::Opaque,
+        };
+        let translucent = GpuTerrainUniforms {
+            alpha_mode: AlphaMode::Blend,
+            ..opaque.clone()
         };
 
-        voxel_materials
-            .iter_mats()
-            .enumerate()
-            .for_each(|(index, material)| {
-                gpu_mats.materials[index].base_color = material.base_color;
-                gpu_mats.materials[index].flags = material.flags.bits();
-                gpu_mats.materials[index].emissive = material.emissive;
-                gpu_mats.materials[index].perceptual_roughness = material.perceptual_roughness;
-                gpu_mats.materials[index].metallic = material.metallic;
-                gpu_mats.materials[index].reflectance = material.reflectance;
-            });
-
-        let chunk_material = materials.add(gpu_mats);
-        commands.insert_resource(ChunkMaterialSingleton(chunk_material.clone()));
-
-        for (_, mut mat) in &mut chunk_entities {
-            *mat = chunk_material.clone();
+        let chunk_material = ChunkMaterialSingleton {
+            opaque: materials.add(opaque),
+            translucent: materials.add(translucent),
+        };
+
+        for (mut mat, translucent) in &mut chunk_entities {
+            *mat = chunk_material.handle(translucent.is_some()).clone();
         }
+
+        commands.insert_resource(chunk_material);
     }
 }
 
-#[derive(Resource, Deref, DerefMut)]
-pub struct ChunkMaterialSingleton(Handle<GpuTerrainUniforms>);
+/// The materials shared by the voxel meshes, one per render pass.
+#[derive(Resource)]
+pub struct ChunkMaterialSingleton {
+    pub opaque: Handle<GpuTerrainUniforms>,
+    pub translucent: Handle<GpuTerrainUniforms>,
+}
+
+impl ChunkMaterialSingleton {
+    pub fn handle(&self, translucent: bool) -> &Handle<GpuTerrainUniforms> {
+        if translucent {
+            &self.translucent
+        } else {
+            &self.opaque
+        }
+    }
+}
 
 impl FromWorld for ChunkMaterialSingleton {
     fn from_world(world: &mut World) -> Self {
         let mut materials = world.resource_mut::<Assets<GpuTerrainUniforms>>();
-        Self(materials.add(GpuTerrainUniforms::default()))
+        Self {
+            opaque: materials.add(GpuTerrainUniforms::default()),
+            translucent: materials.add(GpuTerrainUniforms {
+                alpha_mode: AlphaMode::Blend,
+                ..default()
+            }),
+        }
     }
 }
 
@@ -1,16 +1,369 @@
 use std::marker::PhantomData;
 
-use crate::voxel::{storage::VoxelBuffer, MaterialVoxel};
+use crate::voxel::{material::VoxelMaterialFlags, storage::VoxelBuffer, MaterialVoxel};
 use bevy::{
+    math::{IVec3, Vec3},
     prelude::Mesh,
-    render::mesh::{Indices, VertexAttributeValues},
+    render::{
+        mesh::{Indices, VertexAttributeValues},
+        render_resource::PrimitiveTopology,
+    },
+};
+use block_mesh::{
+    greedy_quads_with_merge_strategy, FaceStrides, GreedyQuadsBuffer, MergeStrategy, MergeVoxel,
+    OrientedBlockFace, UnorientedQuad, Voxel as MeshableVoxel, VoxelVisibility,
+    RIGHT_HANDED_Y_UP_CONFIG,
 };
-use block_mesh::{greedy_quads, GreedyQuadsBuffer, RIGHT_HANDED_Y_UP_CONFIG};
 use ndcopy::copy3;
 use ndshape::{RuntimeShape, Shape};
 
 use super::VoxelTerrainMesh;
 
+/// How the faces of the voxels of a material are meshed, as told by the material flags.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VoxelMeshKind {
+    /// Opaque voxels hiding the faces behind them.
+    #[default]
+    Opaque,
+    /// Voxels drawn in the translucent pass, the faces behind them are still meshed.
+    Translucent,
+    /// Translucent voxels which the actors move through, their faces have no collider.
+    Liquid,
+}
+
+impl VoxelMeshKind {
+    pub fn from_flags(flags: VoxelMaterialFlags) -> Self {
+        if flags.contains(VoxelMaterialFlags::LIQUID) {
+            Self::Liquid
+        } else if flags.contains(VoxelMaterialFlags::TRANSLUCENT) {
+            Self::Translucent
+        } else {
+            Self::Opaque
+        }
+    }
+}
+
+/// The light reaching a voxel, from the sky and from the light emitting voxels.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct VoxelLight {
+    pub sky: u8,
+    pub block: u8,
+}
+
+impl VoxelLight {
+    /// The level of the brightest light, each voxel the light goes through dims it by one level.
+    pub const MAX: u8 = 15;
+
+    /// The light of the voxels standing in the open sky.
+    pub const SKY: Self = Self {
+        sky: Self::MAX,
+        block: 0,
+    };
+}
+
+type FaceMergeValue<T> = (<T as MergeVoxel>::MergeValue, u8, [u16; 4]);
+
+/// A voxel along the way its faces are meshed.
+#[derive(Clone, Copy, Default)]
+struct MeshedVoxel<T> {
+    voxel: T,
+    kind: VoxelMeshKind,
+    /// The ambient occlusion of the corners of each face, see [`face_ambient_occlusion`].
+    ao: [u8; 6],
+    /// The light of the corners of each face, see [`face_light`].
+    light: [[u16; 4]; 6],
+}
+
+impl<T: MaterialVoxel> MeshedVoxel<T> {
+    /// Whether the voxel darkens the corners of the faces next to it.
+    #[inline]
+    fn occludes(&self) -> bool {
+        self.get_visibility() == VoxelVisibility::Opaque
+    }
+
+    /// Whether the face of this voxel facing the specified voxel is visible.
+    #[inline]
+    fn face_visible(&self, neighbour: &Self) -> bool {
+        match (self.get_visibility(), neighbour.get_visibility()) {
+            (VoxelVisibility::Empty, _) => false,
+            (_, VoxelVisibility::Empty) => true,
+            (visibility, VoxelVisibility::Translucent) => visibility == VoxelVisibility::Opaque,
+            (_, VoxelVisibility::Opaque) => false,
+        }
+    }
+
+    /// The value telling whether the specified face can be merged with the same face of another voxel.
+    /// Faces with a different ambient occlusion or light can't be merged, or it would be interpolated across the whole quad.
+    #[inline]
+    fn face_merge_value(&self, face: usize) -> FaceMergeValue<T> {
+        (self.voxel.merge_value(), self.ao[face], self.light[face])
+    }
+}
+
+impl<T: MaterialVoxel> MeshableVoxel for MeshedVoxel<T> {
+    #[inline]
+    fn get_visibility(&self) -> VoxelVisibility {
+        match (self.voxel.get_visibility(), self.kind) {
+            (VoxelVisibility::Empty, _) => VoxelVisibility::Empty,
+            (_, VoxelMeshKind::Opaque) => VoxelVisibility::Opaque,
+            _ => VoxelVisibility::Translucent,
+        }
+    }
+}
+
+/// Merges the faces of the voxels into quads, see [`greedy_quads_with_merge_strategy`].
+/// Unlike the default merger, only the ambient occlusion and the light of the face being merged are compared,
+/// so the other faces of the voxels don't split the quad.
+struct FaceMerger<T>(PhantomData<T>);
+
+impl<T> FaceMerger<T> {
+    /// Returns the index of the face being merged in [`RIGHT_HANDED_Y_UP_CONFIG`].
+    /// The strides of the X, Y and Z axis of the padded buffer are increasing, which tells the normal axis apart.
+    #[inline]
+    fn face_index(strides: &FaceStrides) -> usize {
+        let axis = if strides.n_stride < strides.u_stride.min(strides.v_stride) {
+            0
+        } else if strides.n_stride > strides.u_stride.max(strides.v_stride) {
+            2
+        } else {
+            1
+        };
+
+        if strides.visibility_offset == strides.n_stride {
+            axis + 3
+        } else {
+            axis
+        }
+    }
+}
+
+impl<T: MaterialVoxel> FaceMerger<MeshedVoxel<T>> {
+    /// Returns how many voxels along the row starting at the specified voxel share the merge value of the quad.
+    fn row_width(
+        voxels: &[MeshedVoxel<T>],
+        visited: &[bool],
+        face: usize,
+        value: &FaceMergeValue<T>,
+        strides: &FaceStrides,
+        start: u32,
+        max_width: u32,
+    ) -> u32 {
+        let mut width = 0;
+        let mut index = start;
+        while width < max_width {
+            let voxel = &voxels[index as usize];
+            let neighbour = &voxels[index.wrapping_add(strides.visibility_offset) as usize];
+            if visited[index as usize]
+                || !voxel.face_visible(neighbour)
+                || voxel.face_merge_value(face) != *value
+            {
+                break;
+            }
+
+            width += 1;
+            index = index.wrapping_add(strides.u_stride);
+        }
+
+        width
+    }
+}
+
+impl<T: MaterialVoxel> MergeStrategy for FaceMerger<MeshedVoxel<T>> {
+    type Voxel = MeshedVoxel<T>;
+
+    unsafe fn find_quad(
+        min_index: u32,
+        max_width: u32,
+        max_height: u32,
+        face_strides: &FaceStrides,
+        voxels: &[Self::Voxel],
+        visited: &[bool],
+    ) -> (u32, u32) {
+        let face = Self::face_index(face_strides);
+        let value = voxels[min_index as usize].face_merge_value(face);
+
+        // find the widest quad along U, then how far it extends along V without getting narrower.
+        let width = Self::row_width(
+            voxels,
+            visited,
+            face,
+            &value,
+            face_strides,
+            min_index,
+            max_width,
+        );
+
+        let mut height = 1;
+        let mut row_start = min_index.wrapping_add(face_strides.v_stride);
+        while height < max_height
+            && Self::row_width(
+                voxels,
+                visited,
+                face,
+                &value,
+                face_strides,
+                row_start,
+                width,
+            ) == width
+        {
+            height += 1;
+            row_start = row_start.wrapping_add(face_strides.v_stride);
+        }
+
+        (width, height)
+    }
+}
+
+/// Returns the normal, the U and the V axis of a face, see [`OrientedBlockFace::quad_corners`].
+fn face_axes(face: &OrientedBlockFace) -> [IVec3; 3] {
+    let [min, max_u, max_v, _] = face
+        .quad_corners(&UnorientedQuad {
+            minimum: [0; 3],
+            width: 1,
+            height: 1,
+        })
+        .map(|corner| IVec3::from_array(corner.as_ivec3().to_array()));
+
+    [
+        IVec3::from_array(face.signed_normal().to_array()),
+        max_u - min,
+        max_v - min,
+    ]
+}
+
+/// Computes the ambient occlusion of the four corners of a voxel face, in the order of [`OrientedBlockFace::quad_corners`].
+/// Each corner takes two bits, from `0` for a fully occluded corner up to `3` for an unoccluded one.
+fn face_ambient_occlusion(occludes: impl Fn(IVec3) -> bool, [n, u, v]: [IVec3; 3]) -> u8 {
+    [(-1, -1), (1, -1), (-1, 1), (1, 1)]
+        .into_iter()
+        .enumerate()
+        .fold(0, |ao, (corner, (du, dv))| {
+            let side_u = occludes(n + u * du);
+            let side_v = occludes(n + v * dv);
+            let corner_ao = if side_u && side_v {
+                0
+            } else {
+                3 - side_u as u8 - side_v as u8 - occludes(n + u * du + v * dv) as u8
+            };
+
+            ao | corner_ao << (corner * 2)
+        })
+}
+
+/// Computes the light of the four corners of a voxel face, in the order of [`OrientedBlockFace::quad_corners`],
+/// averaged over the voxels touching the corner in front of the face which don't block the light.
+/// Each corner holds the average sky light in its low byte and the average block light in its high byte,
+/// both scaled by four to keep the fractions.
+fn face_light(
+    light_at: impl Fn(IVec3) -> VoxelLight,
+    occludes: impl Fn(IVec3) -> bool,
+    [n, u, v]: [IVec3; 3],
+) -> [u16; 4] {
+    [(-1, -1), (1, -1), (-1, 1), (1, 1)].map(|(du, dv)| {
+        let side_u = !occludes(n + u * du);
+        let side_v = !occludes(n + v * dv);
+        // the light doesn't leak through the corner when both sides are blocked.
+        let corner = (side_u || side_v) && !occludes(n + u * du + v * dv);
+
+        let (sky, block, count) = [
+            (n, true),
+            (n + u * du, side_u),
+            (n + v * dv, side_v),
+            (n + u * du + v * dv, corner),
+        ]
+        .into_iter()
+        .filter(|(_, lit)| *lit)
+        .map(|(offset, _)| light_at(offset))
+        .fold((0, 0, 0), |(sky, block, count), light| {
+            (
+                sky + light.sky as u16,
+                block + light.block as u16,
+                count + 1,
+            )
+        });
+
+        (sky * 4 / count) | (block * 4 / count) << 8
+    })
+}
+
+/// Returns the light of a face corner computed by [`face_light`], normalized between zero and one.
+#[inline]
+fn corner_light(light: u16) -> [f32; 2] {
+    [light & 0xff, light >> 8].map(|level| level as f32 / (VoxelLight::MAX as f32 * 4.0))
+}
+
+/// Returns the ambient occlusion of the specified corner of a face.
+#[inline]
+fn corner_ambient_occlusion(ao: u8, corner: usize) -> u8 {
+    ao >> (corner * 2) & 3
+}
+
+/// Returns the indices of the two triangles of a quad, split along the diagonal joining the darker corners
+/// so the ambient occlusion is interpolated evenly.
+fn quad_indices(face: &OrientedBlockFace, start: u32, ao: u8) -> [u32; 6] {
+    let indices = face.quad_mesh_indices(start);
+    let [a, b, c, d] = [0, 1, 2, 3].map(|corner| corner_ambient_occlusion(ao, corner));
+
+    if b + c <= a + d {
+        indices
+    } else if indices[1] == start + 1 {
+        [start, start + 1, start + 3, start, start + 3, start + 2]
+    } else {
+        [start, start + 3, start + 1, start, start + 2, start + 3]
+    }
+}
+
+/// The meshes of a voxel buffer, split between the render passes drawing them.
+pub struct VoxelBufferMeshes {
+    pub opaque: Mesh,
+    pub translucent: Mesh,
+    /// Vertices of the faces actors collide with, which leaves out the liquid faces.
+    pub collider_vertices: Vec<Vec3>,
+    pub collider_indices: Vec<[u32; 3]>,
+}
+
+impl Default for VoxelBufferMeshes {
+    fn default() -> Self {
+        Self {
+            opaque: Mesh::new(PrimitiveTopology::TriangleList),
+            translucent: Mesh::new(PrimitiveTopology::TriangleList),
+            collider_vertices: Vec::new(),
+            collider_indices: Vec::new(),
+        }
+    }
+}
+
+/// The vertex data of a mesh being built.
+#[derive(Default)]
+struct MeshData {
+    indices: Vec<u32>,
+    positions: Vec<[f32; 3]>,
+    data: Vec<u32>,
+    light: Vec<[f32; 2]>,
+}
+
+impl MeshData {
+    fn insert_into(self, render_mesh: &mut Mesh) {
+        render_mesh.insert_attribute(
+            Mesh::ATTRIBUTE_POSITION,
+            VertexAttributeValues::Float32x3(self.positions),
+        );
+
+        // see the layout of the voxel data in `voxel_data.wgsl`.
+        render_mesh.insert_attribute(
+            VoxelTerrainMesh::ATTRIBUTE_DATA,
+            VertexAttributeValues::Uint32(self.data),
+        );
+
+        render_mesh.insert_attribute(
+            VoxelTerrainMesh::ATTRIBUTE_LIGHT,
+            VertexAttributeValues::Float32x2(self.light),
+        );
+
+        render_mesh.set_indices(Some(Indices::U32(self.indices)));
+    }
+}
+
 /// Intermediate buffers for greedy meshing of voxel data which are reusable between frames to not allocate.
 pub struct MeshBuffers<T, S: Shape<3, Coord = u32>>
 where
@@ -18,6 +371,8 @@ where
 {
     // A padded buffer to run greedy meshing algorithm on
     scratch_buffer: VoxelBuffer<T, RuntimeShape<u32, 3>>,
+    // The padded buffer along the way each voxel is meshed
+    meshed_buffer: VoxelBuffer<MeshedVoxel<T>, RuntimeShape<u32, 3>>,
     greedy_buffer: GreedyQuadsBuffer,
     _phantom: PhantomData<S>,
 }
@@ -31,18 +386,28 @@ where
 
         Self {
             greedy_buffer: GreedyQuadsBuffer::new(padded_shape.size() as usize),
-            scratch_buffer: VoxelBuffer::<T, RuntimeShape<u32, 3>>::new_empty(padded_shape),
+            scratch_buffer: VoxelBuffer::<T, RuntimeShape<u32, 3>>::new_empty(padded_shape.clone()),
+            meshed_buffer: VoxelBuffer::<MeshedVoxel<T>, RuntimeShape<u32, 3>>::new_empty(
+                padded_shape,
+            ),
             _phantom: Default::default(),
         }
     }
 }
 
 // Processes the voxel data buffer specified as a parameter and generate.
+// `padding` returns the voxels bordering the buffer, at positions relative to the buffer minimum,
+// so no faces are generated between the buffer and solid neighbouring voxels.
+// `light_at` returns the light of the voxels inside and around the buffer, at positions relative to the buffer minimum.
+// `mesh_kinds` tells how the voxels of each material id are meshed, unlisted materials are opaque.
 //todo: don't populate mesh directly, introduce a meshbuilding system.
 pub fn mesh_buffer<T, S>(
     buffer: &VoxelBuffer<T, S>,
+    padding: impl Fn(IVec3) -> T,
+    light_at: impl Fn(IVec3) -> VoxelLight,
     mesh_buffers: &mut MeshBuffers<T, S>,
-    render_mesh: &mut Mesh,
+    mesh_kinds: &[VoxelMeshKind],
+    meshes: &mut VoxelBufferMeshes,
     scale: f32,
 ) where
     T: Copy + Default + MaterialVoxel,
@@ -64,12 +429,79 @@ pub fn mesh_buffer<T, S>(
         [1; 3],
     );
 
-    greedy_quads(
-        mesh_buffers.scratch_buffer.slice(),
-        mesh_buffers.scratch_buffer.shape(),
+    let [max_x, max_y, max_z] = dst_shape.as_array().map(|axis| axis - 1);
+    for z in 0..=max_z {
+        for y in 0..=max_y {
+            let inner_row = y != 0 && y != max_y && z != 0 && z != max_z;
+            for x in 0..=max_x {
+                if inner_row && x != 0 && x != max_x {
+                    continue;
+                }
+
+                *mesh_buffers.scratch_buffer.voxel_at_mut([x, y, z].into()) =
+                    padding(IVec3::new(x as i32, y as i32, z as i32) - IVec3::ONE);
+            }
+        }
+    }
+
+    mesh_buffers
+        .meshed_buffer
+        .slice_mut()
+        .iter_mut()
+        .zip(mesh_buffers.scratch_buffer.slice())
+        .for_each(|(meshed, voxel)| {
+            *meshed = MeshedVoxel {
+                voxel: *voxel,
+                kind: mesh_kinds
+                    .get(voxel.as_mat_id() as usize)
+                    .copied()
+                    .unwrap_or_default(),
+                ao: [0; 6],
+                light: [[0; 4]; 6],
+            }
+        });
+
+    // bake the ambient occlusion and the light of the visible faces, the padding is only sampled.
+    let faces_axes = RIGHT_HANDED_Y_UP_CONFIG.faces.map(|face| face_axes(&face));
+    let meshed_buffer = &mut mesh_buffers.meshed_buffer;
+    for z in 1..max_z {
+        for y in 1..max_y {
+            for x in 1..max_x {
+                let pos = IVec3::new(x as i32, y as i32, z as i32);
+                let at = |offset: IVec3| {
+                    meshed_buffer.voxel_at((pos + offset).as_uvec3().to_array().into())
+                };
+                let meshed = at(IVec3::ZERO);
+                if meshed.get_visibility() == VoxelVisibility::Empty {
+                    continue;
+                }
+
+                let mut ao = [0; 6];
+                let mut light = [[0; 4]; 6];
+                for (face, axes) in faces_axes.into_iter().enumerate() {
+                    if meshed.face_visible(&at(axes[0])) {
+                        ao[face] = face_ambient_occlusion(|offset| at(offset).occludes(), axes);
+                        light[face] = face_light(
+                            |offset| light_at(pos + offset - IVec3::ONE),
+                            |offset| at(offset).occludes(),
+                            axes,
+                        );
+                    }
+                }
+
+                let meshed = meshed_buffer.voxel_at_mut(pos.as_uvec3().to_array().into());
+                meshed.ao = ao;
+                meshed.light = light;
+            }
+        }
+    }
+
+    greedy_quads_with_merge_strategy::<_, _, FaceMerger<_>>(
+        mesh_buffers.meshed_buffer.slice(),
+        mesh_buffers.meshed_buffer.shape(),
         [0; 3],
         mesh_buffers
-            .scratch_buffer
+            .meshed_buffer
             .shape()
             .as_array()
             .map(|axis| axis - 1),
@@ -77,11 +509,10 @@ pub fn mesh_buffer<T, S>(
         &mut mesh_buffers.greedy_buffer,
     );
 
-    let num_indices = mesh_buffers.greedy_buffer.quads.num_quads() * 6;
-    let num_vertices = mesh_buffers.greedy_buffer.quads.num_quads() * 4;
-    let mut indices = Vec::with_capacity(num_indices);
-    let mut positions = Vec::with_capacity(num_vertices);
-    let mut data = Vec::with_capacity(num_vertices);
+    let mut opaque = MeshData::default();
+    let mut translucent = MeshData::default();
+    meshes.collider_vertices.clear();
+    meshes.collider_indices.clear();
 
     //normal face index depends on the quad orientation config
     for (block_face_normal_index, (group, face)) in mesh_buffers
@@ -94,27 +525,46 @@ pub fn mesh_buffer<T, S>(
         .enumerate()
     {
         for quad in group.iter() {
-            indices.extend_from_slice(&face.quad_mesh_indices(positions.len() as u32));
-            positions.extend_from_slice(&face.quad_mesh_positions(quad, scale));
-            data.extend_from_slice(
-                &[(block_face_normal_index as u32) << 8u32
-                    | buffer
-                        .voxel_at(quad.minimum.map(|x| x - 1).into())
-                        .as_mat_id() as u32; 4],
-            );
-        }
-    }
+            let MeshedVoxel {
+                voxel,
+                kind,
+                ao,
+                light,
+            } = mesh_buffers.meshed_buffer.voxel_at(quad.minimum.into());
+            let ao = ao[block_face_normal_index];
+            let light = light[block_face_normal_index];
+            // the quads lie in the padded buffer, shifted by one voxel at the full resolution.
+            let positions = face
+                .quad_mesh_positions(quad, scale)
+                .map(|position| position.map(|x| x - scale + 1.0));
 
-    render_mesh.insert_attribute(
-        Mesh::ATTRIBUTE_POSITION,
-        VertexAttributeValues::Float32x3(positions),
-    );
+            if kind != VoxelMeshKind::Liquid {
+                let start = meshes.collider_vertices.len() as u32;
+                let [a, b, c, d, e, f] = face.quad_mesh_indices(start);
+                meshes.collider_indices.extend([[a, b, c], [d, e, f]]);
+                meshes
+                    .collider_vertices
+                    .extend(positions.map(Vec3::from_array));
+            }
 
-    //todo: in the future we might want to encode all the information onto a single uint32
-    render_mesh.insert_attribute(
-        VoxelTerrainMesh::ATTRIBUTE_DATA,
-        VertexAttributeValues::Uint32(data),
-    );
+            let mesh = match kind {
+                VoxelMeshKind::Opaque => &mut opaque,
+                VoxelMeshKind::Translucent | VoxelMeshKind::Liquid => &mut translucent,
+            };
+
+            mesh.indices
+                .extend_from_slice(&quad_indices(face, mesh.positions.len() as u32, ao));
+            mesh.positions.extend_from_slice(&positions);
+            mesh.data.extend((0..4).map(|corner| {
+                (voxel.state() as u32) << 24u32
+                    | (corner_ambient_occlusion(ao, corner) as u32) << 19u32
+                    | (block_face_normal_index as u32) << 16u32
+                    | voxel.as_mat_id() as u32
+            }));
+            mesh.light.extend(light.map(corner_light));
+        }
+    }
 
-    render_mesh.set_indices(Some(Indices::U32(indices.clone())));
+    opaque.insert_into(&mut meshes.opaque);
+    translucent.insert_into(&mut meshes.translucent);
 }
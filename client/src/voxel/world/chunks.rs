@@ -1,18 +1,20 @@
 use bevy::{
-    math::IVec3,
+    app::AppExit,
+    math::{IVec3, UVec3},
     prelude::{
-        in_state, Changed, Commands, CoreSet, Entity, GlobalTransform, IntoSystemConfig,
-        IntoSystemConfigs, IntoSystemSetConfig, OnUpdate, Plugin, Query, Res, ResMut, Resource,
-        SystemSet, With,
+        in_state, resource_equals, Changed, Commands, CoreSet, DespawnRecursiveExt, Entity,
+        EventReader, EventWriter, GlobalTransform, IntoSystemConfig, IntoSystemConfigs,
+        IntoSystemSetConfig, OnUpdate, Plugin, Query, Res, ResMut, Resource, SystemSet, With,
     },
     utils::{HashMap, HashSet},
 };
 use float_ord::FloatOrd;
 
-use super::{Chunk, ChunkShape, CHUNK_LENGTH};
-use crate::voxel::storage::ChunkMap;
+use super::{light::ChunkLightMap, terrain::TerrainGenMode, Chunk, ChunkShape, CHUNK_LENGTH};
+use crate::voxel::storage::{ChunkMap, PaletteBuffer, RegionStore};
 use crate::voxel::Voxel;
 use crate::{voxel::player, GameState};
+use common::voxel::chunks_in_view;
 
 /// Updates the current chunk position for the current player.
 fn update_player_pos(
@@ -40,30 +42,13 @@ fn update_view_chunks(
 ) {
     // quick n dirty circular chunk loading.
     //perf: optimize this.
-    for x in -view_radius.horizontal..view_radius.horizontal {
-        for z in -view_radius.horizontal..view_radius.horizontal {
-            for y in -view_radius.vertical..view_radius.vertical {
-                if x.pow(2) + z.pow(2) >= view_radius.horizontal.pow(2) {
-                    continue;
-                }
-
-                let chunk_key = {
-                    let mut pos: IVec3 = player_pos.chunk_min
-                        + IVec3::new(
-                            x * CHUNK_LENGTH as i32,
-                            y * CHUNK_LENGTH as i32,
-                            z * CHUNK_LENGTH as i32,
-                        );
-
-                    pos.y = pos.y.max(0);
-
-                    pos
-                };
-
-                if chunk_entities.entity(chunk_key).is_none() {
-                    chunk_command_queue.create.push(chunk_key);
-                }
-            }
+    for chunk_key in chunks_in_view(
+        player_pos.chunk_min,
+        view_radius.horizontal,
+        view_radius.vertical,
+    ) {
+        if chunk_entities.entity(chunk_key).is_none() {
+            chunk_command_queue.create.push(chunk_key);
         }
     }
 
@@ -99,23 +84,61 @@ fn create_chunks(
         .for_each(|request| chunk_entities.attach_entity(request, cmds.spawn(Chunk(request)).id()));
 }
 
+/// Destroys the requested chunks and queues their voxel data to be written to disk.
+/// Chunks streamed by the server are saved by the server instead.
+#[allow(clippy::too_many_arguments)]
 fn destroy_chunks(
     mut chunks_command_queue: ResMut<ChunkCommandQueue>,
     mut chunks: ResMut<ChunkMap<Voxel, ChunkShape>>,
     mut chunk_entities: ResMut<ChunkEntities>,
+    region_store: Res<RegionStore>,
+    terrain_gen_mode: Res<TerrainGenMode>,
+    mut light: ResMut<ChunkLightMap>,
+    mut unloaded: EventWriter<ChunkUnloaded>,
     mut cmds: Commands,
 ) {
     for command in chunks_command_queue.destroy.drain(..) {
         cmds.entity(chunk_entities.detach_entity(command).unwrap())
-            .despawn();
-        chunks.remove(command);
+            .despawn_recursive();
+        unloaded.send(ChunkUnloaded(command));
+        light.remove(command);
+        if let Some(buffer) = chunks.remove(command) {
+            if *terrain_gen_mode == TerrainGenMode::Local {
+                region_store.queue_save(command, buffer);
+            }
+        }
     }
+
+    region_store.flush_in_background();
+}
+
+/// Writes every loaded chunk to disk before the app exits.
+fn save_chunks_on_exit(
+    mut exit_events: EventReader<AppExit>,
+    chunks: Res<ChunkMap<Voxel, ChunkShape>>,
+    chunk_entities: Res<ChunkEntities>,
+    region_store: Res<RegionStore>,
+) {
+    if exit_events.iter().last().is_none() {
+        return;
+    }
+
+    for key in chunk_entities.iter_keys() {
+        if let Some(buffer) = chunks.buffer_at(*key) {
+            region_store.queue_save(*key, buffer.clone());
+        }
+    }
+
+    region_store.flush();
 }
 
 fn clear_dirty_chunks(mut dirty_chunks: ResMut<DirtyChunks>) {
-    dirty_chunks.0.clear();
+    dirty_chunks.clear();
 }
 
+/// Sent when a loaded chunk is destroyed along its voxel data.
+pub struct ChunkUnloaded(pub IVec3);
+
 /// Label for the stage housing the chunk loading systems.
 #[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Hash, SystemSet)]
 pub struct ChunkLoadingSet;
@@ -154,22 +177,76 @@ impl ChunkEntities {
     }
 }
 
-/// Holds the dirty chunk for the current frame.
+/// Holds the dirty chunk for the current frame, along the voxels edited during the frame whose light is updated.
 #[derive(Default, Resource)]
-pub struct DirtyChunks(HashSet<IVec3>);
+pub struct DirtyChunks {
+    chunks: HashSet<IVec3>,
+    edited: Vec<IVec3>,
+}
 
 #[allow(dead_code)]
 impl DirtyChunks {
     pub fn mark_dirty(&mut self, chunk: IVec3) {
-        self.0.insert(chunk);
+        self.chunks.insert(chunk);
+    }
+
+    /// Marks dirty a newly loaded chunk along the 26 chunks around it,
+    /// as the borders of the neighbours are meshed against the voxels of the chunk.
+    /// The neighbours are left alone when the chunk is empty, as they were meshed against empty voxels already.
+    pub fn mark_loaded(&mut self, chunk: IVec3, voxels: &PaletteBuffer<Voxel, ChunkShape>) {
+        if voxels.is_uniform() && voxels.voxel_at(UVec3::ZERO).is_empty() {
+            self.mark_dirty(chunk);
+        } else {
+            self.mark_neighbours_dirty(chunk, IVec3::NEG_ONE, IVec3::ONE);
+        }
+    }
+
+    /// Marks dirty the chunk containing the edited voxel at the specified world position,
+    /// as well as the neighbouring chunks whose mesh padding holds the voxel.
+    /// The chunks whose light changes along are marked dirty once the light is updated.
+    pub fn mark_voxel_dirty(&mut self, pos: IVec3) {
+        let chunk_min = !IVec3::splat((CHUNK_LENGTH - 1) as i32) & pos;
+        let local = pos - chunk_min;
+
+        // the offsets of the neighbours along each axis, zero when the voxel isn't on the borders.
+        let min = IVec3::select(local.cmpeq(IVec3::ZERO), IVec3::NEG_ONE, IVec3::ZERO);
+        let max = IVec3::select(
+            local.cmpeq(IVec3::splat(CHUNK_LENGTH as i32 - 1)),
+            IVec3::ONE,
+            IVec3::ZERO,
+        );
+
+        self.mark_neighbours_dirty(chunk_min, min, max);
+        self.edited.push(pos);
+    }
+
+    /// Marks dirty the chunks lying between the specified offsets from a chunk, in chunks.
+    fn mark_neighbours_dirty(&mut self, chunk: IVec3, min: IVec3, max: IVec3) {
+        for z in min.z..=max.z {
+            for y in min.y..=max.y {
+                for x in min.x..=max.x {
+                    self.mark_dirty(chunk + IVec3::new(x, y, z) * CHUNK_LENGTH as i32);
+                }
+            }
+        }
     }
 
     pub fn iter_dirty(&self) -> impl Iterator<Item = &IVec3> {
-        self.0.iter()
+        self.chunks.iter()
+    }
+
+    /// Returns the voxels edited during the frame, in the order they were edited.
+    pub fn iter_edited(&self) -> impl Iterator<Item = &IVec3> {
+        self.edited.iter()
     }
 
     pub fn num_dirty(&self) -> usize {
-        self.0.len()
+        self.chunks.len()
+    }
+
+    fn clear(&mut self) {
+        self.chunks.clear();
+        self.edited.clear();
     }
 }
 
@@ -213,6 +290,7 @@ impl Plugin for VoxelWorldChunkingPlugin {
         })
         .init_resource::<ChunkCommandQueue>()
         .init_resource::<DirtyChunks>()
+        .add_event::<ChunkUnloaded>()
         .configure_set(ChunkLoadingSet.in_set(OnUpdate(GameState::Game)))
         .add_systems(
             (update_player_pos, update_view_chunks, create_chunks)
@@ -228,6 +306,12 @@ impl Plugin for VoxelWorldChunkingPlugin {
             clear_dirty_chunks
                 .run_if(in_state(GameState::Game))
                 .in_base_set(CoreSet::Last),
+        )
+        .add_system(
+            save_chunks_on_exit
+                .run_if(in_state(GameState::Game))
+                .run_if(resource_equals(TerrainGenMode::Local))
+                .in_base_set(CoreSet::Last),
         );
     }
 }
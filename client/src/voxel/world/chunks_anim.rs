@@ -28,14 +28,15 @@ fn attach_chunk_animation(
     mut commands: Commands,
 ) {
     removed_chunk_meshes.iter().for_each(|entity| {
-        if ready_chunks.contains(entity) {
-            commands.entity(entity).insert(ChunkSpawnAnimation {
-                start_time: time.elapsed_seconds(),
-            });
-            if let Ok((mut transform, mut visibility, chunk)) = ready_chunks.get_mut(entity) {
+        // only animate chunks meshed for the first time, not the ones remeshed after an edit.
+        if let Ok((mut transform, mut visibility, chunk)) = ready_chunks.get_mut(entity) {
+            if *visibility == Visibility::Hidden {
+                commands.entity(entity).insert(ChunkSpawnAnimation {
+                    start_time: time.elapsed_seconds(),
+                });
                 *visibility = Visibility::Visible;
                 transform.translation.y = chunk.0.y as f32 - ANIMATION_HEIGHT;
-            };
+            }
         }
     });
 }
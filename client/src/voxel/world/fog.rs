@@ -0,0 +1,150 @@
+use bevy::{
+    pbr::{FogFalloff, FogSettings},
+    prelude::{
+        in_state, resource_changed, Assets, Camera3d, Color, Commands, CoreSet, DetectChanges,
+        Entity, IntoSystemConfig, OnUpdate, Plugin, Query, Res, ResMut, Resource, With,
+    },
+};
+
+use super::{chunks::ChunkLoadRadius, CHUNK_LENGTH};
+use crate::{
+    voxel::render::{ChunkMaterialSingleton, GpuDistanceFog, GpuTerrainUniforms},
+    GameState,
+};
+
+/// Distance over which the linear fog thickens up to the edge of the loaded terrain.
+const FOG_DEPTH: f32 = 4.5 * CHUNK_LENGTH as f32;
+
+/// How the distance fog thickens with the distance from the camera.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DistanceFogFalloff {
+    /// The fog thickens evenly from its start distance to its end distance.
+    #[default]
+    Linear,
+    /// The fog thickens from the camera, quickly at first, hiding most of the terrain at its end distance.
+    Exponential,
+    /// The fog thickens from the camera, slowly at first, hiding most of the terrain at its end distance.
+    ExponentialSquared,
+}
+
+/// The fog hiding the edge of the loaded terrain, applied to the terrain and to the other meshes alike.
+/// Its distances follow the [`ChunkLoadRadius`], and its colour follows the sky.
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct DistanceFog {
+    pub color: Color,
+    /// Distance from the camera the linear fog starts at.
+    pub start: f32,
+    /// Distance from the camera past which the fog hides everything.
+    pub end: f32,
+    pub falloff: DistanceFogFalloff,
+}
+
+impl Default for DistanceFog {
+    fn default() -> Self {
+        let fog = GpuDistanceFog::default();
+        Self {
+            color: fog.color,
+            start: fog.start,
+            end: fog.end,
+            falloff: DistanceFogFalloff::Linear,
+        }
+    }
+}
+
+impl DistanceFog {
+    fn bevy_falloff(&self) -> FogFalloff {
+        match self.falloff {
+            DistanceFogFalloff::Linear => FogFalloff::Linear {
+                start: self.start,
+                end: self.end,
+            },
+            DistanceFogFalloff::Exponential => FogFalloff::from_visibility(self.end),
+            DistanceFogFalloff::ExponentialSquared => FogFalloff::from_visibility_squared(self.end),
+        }
+    }
+
+    /// Returns the fog as laid out for the terrain shader, with the same falloff as [`FogSettings`].
+    fn gpu_fog(&self) -> GpuDistanceFog {
+        let (falloff, density) = match self.bevy_falloff() {
+            FogFalloff::Exponential { density } => (GpuDistanceFog::FALLOFF_EXPONENTIAL, density),
+            FogFalloff::ExponentialSquared { density } => {
+                (GpuDistanceFog::FALLOFF_EXPONENTIAL_SQUARED, density)
+            }
+            _ => (GpuDistanceFog::FALLOFF_LINEAR, 0.0),
+        };
+
+        GpuDistanceFog {
+            color: self.color,
+            start: self.start,
+            end: self.end,
+            density,
+            falloff,
+        }
+    }
+
+    fn settings(&self) -> FogSettings {
+        FogSettings {
+            color: self.color,
+            falloff: self.bevy_falloff(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Moves the fog along with the edge of the loaded terrain when the chunk loading radius changes.
+/// The debug UI changes the radius every frame it is displayed, so the fog is only touched when it moves.
+fn fit_fog_to_load_radius(load_radius: Res<ChunkLoadRadius>, mut fog: ResMut<DistanceFog>) {
+    let end = (load_radius.horizontal * CHUNK_LENGTH as i32) as f32;
+    let start = (end - FOG_DEPTH).max(0.0);
+
+    if fog.start != start || fog.end != end {
+        fog.start = start;
+        fog.end = end;
+    }
+}
+
+/// Applies the fog to the terrain materials and to the cameras, so the meshes which aren't terrain fade out the same way.
+fn apply_distance_fog(
+    mut commands: Commands,
+    fog: Res<DistanceFog>,
+    chunk_material: Res<ChunkMaterialSingleton>,
+    mut materials: ResMut<Assets<GpuTerrainUniforms>>,
+    mut cameras: Query<(Entity, Option<&mut FogSettings>), With<Camera3d>>,
+) {
+    if fog.is_changed() || chunk_material.is_changed() {
+        for handle in [&chunk_material.opaque, &chunk_material.translucent] {
+            if let Some(material) = materials.get_mut(handle) {
+                material.fog = fog.gpu_fog();
+            }
+        }
+    }
+
+    for (entity, settings) in cameras.iter_mut() {
+        match settings {
+            Some(mut settings) if fog.is_changed() => *settings = fog.settings(),
+            Some(_) => {}
+            None => {
+                commands.entity(entity).insert(fog.settings());
+            }
+        }
+    }
+}
+
+/// Fades the distant meshes into a fog tied to the view distance.
+pub struct DistanceFogPlugin;
+
+impl Plugin for DistanceFogPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.init_resource::<DistanceFog>()
+            .add_system(
+                fit_fog_to_load_radius
+                    .run_if(resource_changed::<ChunkLoadRadius>())
+                    .in_set(OnUpdate(GameState::Game)),
+            )
+            .add_system(
+                apply_distance_fog
+                    .run_if(in_state(GameState::Game))
+                    .in_base_set(CoreSet::PostUpdate),
+            );
+    }
+}
@@ -0,0 +1,240 @@
+use bevy::{prelude::*, utils::HashMap};
+use bevy_rapier3d::prelude::{Collider, CollisionGroups, Group, LockedAxes, RigidBody};
+use common::{VoxelEdit, VoxelFall};
+
+use super::{chunks::DirtyChunks, meshing::ChunkMeshingSet, ChunkShape, Voxel};
+use crate::{
+    voxel::{
+        material::{VoxelMaterialFlags, VoxelMaterialRegistry},
+        storage::ChunkMap,
+    },
+    GameState,
+};
+
+/// Seconds after which a voxel still falling, such as one falling into unloaded chunks,
+/// is put back at the lowest voxel it can rest in of the loaded part of its column.
+const FALLING_VOXEL_LIFETIME: f32 = 10.0;
+
+/// Collision group of the falling voxels, which only collide with each other.
+/// They land on the voxels rather than on the chunk colliders, which lag behind the edits until the chunks are meshed again.
+const FALLING_VOXEL_GROUP: Group = Group::GROUP_10;
+
+/// A voxel falling down as a physics body, turned back into a voxel once it lands.
+#[derive(Component)]
+pub struct FallingVoxel {
+    pub voxel: Voxel,
+    /// The voxel it collapsed from, sent to the server along where it lands.
+    origin: IVec3,
+    /// Seconds the voxel has been falling for.
+    elapsed: f32,
+    /// Height of the center of the voxel during the previous frame,
+    /// every voxel crossed since then being checked for a landing.
+    previous_y: f32,
+}
+
+/// The cube mesh drawn for the falling voxels.
+#[derive(Resource)]
+struct FallingVoxelMesh(Handle<Mesh>);
+
+impl FromWorld for FallingVoxelMesh {
+    fn from_world(world: &mut World) -> Self {
+        let mut meshes = world.resource_mut::<Assets<Mesh>>();
+        Self(meshes.add(Mesh::from(shape::Cube { size: 1.0 })))
+    }
+}
+
+/// Whether the material of a voxel has all the specified flags.
+fn has_flags(registry: &VoxelMaterialRegistry, voxel: Voxel, flags: VoxelMaterialFlags) -> bool {
+    !voxel.is_empty()
+        && registry
+            .get_by_id(voxel.id)
+            .is_some_and(|mat| mat.flags.contains(flags))
+}
+
+/// The material drawn for the falling voxels of each material id, shared by all the voxels of a material.
+#[derive(Default, Resource)]
+struct FallingVoxelMaterials(HashMap<u16, Handle<StandardMaterial>>);
+
+/// Whether a voxel holds up the voxel above it, liquids and voxels of unloaded chunks holding nothing.
+fn supports(registry: &VoxelMaterialRegistry, voxel: Option<Voxel>) -> bool {
+    voxel.is_some_and(|voxel| {
+        !voxel.is_empty() && !has_flags(registry, voxel, VoxelMaterialFlags::LIQUID)
+    })
+}
+
+/// Turns the gravity affected voxels left without support by the voxel edits into falling voxels,
+/// along the ones stacked on top of them.
+#[allow(clippy::too_many_arguments)]
+fn collapse_unsupported_voxels(
+    mut commands: Commands,
+    mut voxel_edits: EventReader<VoxelEdit>,
+    registry: Res<VoxelMaterialRegistry>,
+    mut chunks: ResMut<ChunkMap<Voxel, ChunkShape>>,
+    mut dirty_chunks: ResMut<DirtyChunks>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut falling_materials: ResMut<FallingVoxelMaterials>,
+    mesh: Res<FallingVoxelMesh>,
+) {
+    // the colors of the materials may have been edited, the voxels falling from now on use the new ones.
+    if registry.is_changed() {
+        falling_materials.0.clear();
+    }
+
+    let edited: Vec<IVec3> = voxel_edits.iter().map(|edit| edit.position).collect();
+
+    let is_unsupported = |chunks: &ChunkMap<Voxel, ChunkShape>, pos: IVec3| {
+        chunks
+            .voxel_at(pos)
+            .is_some_and(|voxel| has_flags(&registry, voxel, VoxelMaterialFlags::GRAVITY))
+            && !supports(&registry, chunks.voxel_at(pos - IVec3::Y))
+    };
+
+    for mut pos in edited {
+        // either the edited voxel falls, or the voxel above it may have lost its support.
+        if !is_unsupported(&chunks, pos) {
+            pos += IVec3::Y;
+        }
+
+        while is_unsupported(&chunks, pos) {
+            let Some(mut current) = chunks.voxel_at_mut(pos) else {
+                break;
+            };
+            let voxel = *current;
+            *current = Voxel::EMPTY_VOXEL;
+            drop(current);
+
+            let material = falling_materials
+                .0
+                .entry(voxel.id)
+                .or_insert_with(|| {
+                    let base_color = registry
+                        .get_by_id(voxel.id)
+                        .map_or(Color::WHITE, |mat| mat.base_color);
+                    materials.add(base_color.into())
+                })
+                .clone();
+            let translation = pos.as_vec3() + Vec3::splat(0.5);
+
+            commands.spawn((
+                FallingVoxel {
+                    voxel,
+                    origin: pos,
+                    elapsed: 0.0,
+                    previous_y: translation.y,
+                },
+                PbrBundle {
+                    mesh: mesh.0.clone(),
+                    material,
+                    transform: Transform::from_translation(translation),
+                    ..default()
+                },
+                RigidBody::Dynamic,
+                LockedAxes::ROTATION_LOCKED,
+                Collider::cuboid(0.49, 0.49, 0.49),
+                CollisionGroups::new(FALLING_VOXEL_GROUP, FALLING_VOXEL_GROUP),
+            ));
+
+            dirty_chunks.mark_voxel_dirty(pos);
+
+            pos += IVec3::Y;
+        }
+    }
+}
+
+/// Turns the falling voxels back into voxels once they reach a voxel holding them up, and sends their fall to the server.
+/// The voxels crossed during the frame are all checked, so fast voxels don't fall through thin floors.
+fn land_falling_voxels(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut falling_voxels: Query<(Entity, &mut FallingVoxel, &Transform)>,
+    registry: Res<VoxelMaterialRegistry>,
+    mut chunks: ResMut<ChunkMap<Voxel, ChunkShape>>,
+    mut dirty_chunks: ResMut<DirtyChunks>,
+    mut voxel_falls: EventWriter<VoxelFall>,
+) {
+    for (entity, mut falling, transform) in falling_voxels.iter_mut() {
+        falling.elapsed += time.delta_seconds();
+
+        let column = transform.translation.floor().as_ivec3();
+        let bottom = transform.translation.y - 0.5;
+
+        // from the voxel the center was in during the last frame, down to the highest voxel whose top the bottom reached.
+        let highest = (falling.previous_y.floor() as i32).max(column.y);
+        let lowest = bottom.ceil() as i32 - 1;
+        falling.previous_y = transform.translation.y;
+
+        let support = (lowest..=highest).rev().find(|y| {
+            supports(
+                &registry,
+                chunks.voxel_at(IVec3::new(column.x, *y, column.z)),
+            )
+        });
+
+        let landing = match support {
+            Some(support) => {
+                let mut pos = IVec3::new(column.x, support, column.z);
+                while supports(&registry, chunks.voxel_at(pos)) {
+                    pos += IVec3::Y;
+                }
+                Some(pos)
+            }
+            // the voxel would be lost otherwise, as it already left its origin.
+            None if falling.elapsed > FALLING_VOXEL_LIFETIME => {
+                resting_voxel(&registry, &chunks, falling.origin, column.y)
+            }
+            None => continue,
+        };
+
+        commands.entity(entity).despawn_recursive();
+
+        let Some(landing) = landing else {
+            continue;
+        };
+        let Some(mut current) = chunks.voxel_at_mut(landing) else {
+            continue;
+        };
+        *current = falling.voxel;
+        drop(current);
+
+        dirty_chunks.mark_voxel_dirty(landing);
+        voxel_falls.send(VoxelFall {
+            origin: falling.origin,
+            landing,
+            voxel: falling.voxel,
+        });
+    }
+}
+
+/// Returns the lowest voxel of a column a falling voxel can rest in, between the specified height and its origin,
+/// with only loaded voxels holding nothing up between them.
+fn resting_voxel(
+    registry: &VoxelMaterialRegistry,
+    chunks: &ChunkMap<Voxel, ChunkShape>,
+    origin: IVec3,
+    height: i32,
+) -> Option<IVec3> {
+    (height.min(origin.y)..=origin.y)
+        .rev()
+        .map(|y| IVec3::new(origin.x, y, origin.z))
+        .take_while(|pos| {
+            chunks.voxel_at(*pos).is_some() && !supports(registry, chunks.voxel_at(*pos))
+        })
+        .last()
+}
+
+/// Makes the voxels of the materials flagged with [`VoxelMaterialFlags::GRAVITY`] fall down when they lose their support.
+pub struct VoxelGravityPlugin;
+
+impl Plugin for VoxelGravityPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.init_resource::<FallingVoxelMesh>()
+            .init_resource::<FallingVoxelMaterials>()
+            .add_event::<VoxelFall>()
+            .add_systems(
+                (collapse_unsupported_voxels, land_falling_voxels)
+                    .chain()
+                    .in_set(OnUpdate(GameState::Game))
+                    .before(ChunkMeshingSet),
+            );
+    }
+}
@@ -0,0 +1,609 @@
+use std::collections::VecDeque;
+
+use bevy::{math::IVec3, prelude::Resource, utils::HashMap};
+use ndshape::ConstShape;
+
+use super::{chunks::DirtyChunks, ChunkShape, Voxel, CHUNK_LENGTH};
+use crate::voxel::{
+    render::{VoxelLight, VoxelMeshKind},
+    storage::{ChunkMap, PaletteBuffer, VoxelBuffer},
+};
+
+/// The offsets of the six neighbours of a voxel, the voxel below being the third one.
+const NEIGHBOURS: [IVec3; 6] = [
+    IVec3::NEG_X,
+    IVec3::X,
+    IVec3::NEG_Y,
+    IVec3::Y,
+    IVec3::NEG_Z,
+    IVec3::Z,
+];
+
+/// The index of the voxel below in [`NEIGHBOURS`].
+const BELOW: usize = 2;
+
+/// The light of the loaded chunks, flood filled from the sky and from the light emitting voxels.
+/// Each voxel holds its sky light in its low four bits and its block light in its high four bits, see [`unpack_light`].
+#[derive(Resource)]
+pub struct ChunkLightMap(ChunkMap<u8, ChunkShape>);
+
+impl Default for ChunkLightMap {
+    fn default() -> Self {
+        Self(ChunkMap::new(ChunkShape {}))
+    }
+}
+
+impl ChunkLightMap {
+    /// Whether the light of the chunk with the specified key was computed.
+    pub fn is_lit(&self, key: IVec3) -> bool {
+        self.0.exists(key)
+    }
+
+    pub fn buffer_at(&self, key: IVec3) -> Option<&PaletteBuffer<u8, ChunkShape>> {
+        self.0.buffer_at(key)
+    }
+
+    pub fn remove(&mut self, key: IVec3) {
+        self.0.remove(key);
+    }
+
+    /// Forgets the light of every chunk, so they are all lit again.
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// Returns the light packed in a voxel of a [`ChunkLightMap`].
+#[inline]
+pub fn unpack_light(light: u8) -> VoxelLight {
+    VoxelLight {
+        sky: light & 0xf,
+        block: light >> 4,
+    }
+}
+
+/// Whether a voxel stops the light from going through it.
+#[inline]
+fn blocks_light(voxel: Voxel, mesh_kinds: &[VoxelMeshKind]) -> bool {
+    !voxel.is_empty()
+        && mesh_kinds
+            .get(voxel.id as usize)
+            .copied()
+            .unwrap_or_default()
+            == VoxelMeshKind::Opaque
+}
+
+/// The two kinds of light, spreading the same way except for the sunlight going straight down without dimming.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum LightChannel {
+    Sky,
+    Block,
+}
+
+impl LightChannel {
+    #[inline]
+    fn get(self, light: u8) -> u8 {
+        match self {
+            Self::Sky => light & 0xf,
+            Self::Block => light >> 4,
+        }
+    }
+
+    #[inline]
+    fn with(self, light: u8, level: u8) -> u8 {
+        match self {
+            Self::Sky => light & 0xf0 | level,
+            Self::Block => light & 0xf | level << 4,
+        }
+    }
+
+    /// Returns the level of the light reaching a neighbour of a voxel lit at the specified level.
+    #[inline]
+    fn spread(self, level: u8, neighbour: usize) -> u8 {
+        if self == Self::Sky && neighbour == BELOW && level == VoxelLight::MAX {
+            VoxelLight::MAX
+        } else {
+            level.saturating_sub(1)
+        }
+    }
+}
+
+/// A voxel of a chunk touched by a light update, as the slot of the chunk along the index of the voxel in it.
+type LightNode = (usize, usize);
+
+/// A lit chunk touched by a light update, decompressed so the light spreads through it quickly.
+struct LitChunk {
+    key: IVec3,
+    voxels: VoxelBuffer<Voxel, ChunkShape>,
+    light: VoxelBuffer<u8, ChunkShape>,
+    changed: bool,
+    /// The chunks around this one whose mesh padding holds a voxel whose light changed,
+    /// indexed by `x + y * 3 + z * 9` from the chunk at the offset `(-1, -1, -1)`.
+    dirty_neighbours: [bool; 27],
+}
+
+/// Spreads the light through the loaded chunks as they are loaded and edited, only touching the voxels whose light changes.
+/// The light reaching the voxels is written back to the [`ChunkLightMap`] by [`LightUpdate::finish`].
+pub struct LightUpdate<'a> {
+    chunks: &'a ChunkMap<Voxel, ChunkShape>,
+    light: &'a mut ChunkLightMap,
+    mesh_kinds: &'a [VoxelMeshKind],
+    light_emission: &'a [u8],
+    /// The slots of the chunks in `lit`, `None` for the chunks which aren't lit.
+    slots: HashMap<IVec3, Option<usize>>,
+    lit: Vec<LitChunk>,
+}
+
+impl<'a> LightUpdate<'a> {
+    /// `light_emission` is the level of the light emitted by the voxels of each material id.
+    pub fn new(
+        chunks: &'a ChunkMap<Voxel, ChunkShape>,
+        light: &'a mut ChunkLightMap,
+        mesh_kinds: &'a [VoxelMeshKind],
+        light_emission: &'a [u8],
+    ) -> Self {
+        Self {
+            chunks,
+            light,
+            mesh_kinds,
+            light_emission,
+            slots: HashMap::default(),
+            lit: Vec::new(),
+        }
+    }
+
+    /// Lights a newly loaded chunk, along the light it lets into the lit chunks around it.
+    /// The sky is assumed to be open above the chunk until the chunk above it is lit.
+    pub fn light_chunk(&mut self, key: IVec3) {
+        let Some(voxels) = self.chunks.buffer_at(key) else {
+            return;
+        };
+
+        let slot = self.lit.len();
+        self.slots.insert(key, Some(slot));
+        self.lit.push(LitChunk {
+            key,
+            voxels: voxels.to_buffer(),
+            light: VoxelBuffer::new_empty(ChunkShape {}),
+            changed: true,
+            dirty_neighbours: [false; 27],
+        });
+
+        let length = CHUNK_LENGTH as i32;
+        let mut sky = VecDeque::new();
+        let mut block = VecDeque::new();
+
+        for index in 0..ChunkShape::USIZE {
+            let emission = self.emission((slot, index));
+            if emission > 0 {
+                self.set((slot, index), LightChannel::Block, emission);
+                block.push_back((slot, index));
+            }
+        }
+
+        // the light of the lit neighbours spreads from the voxels facing the chunk.
+        for (neighbour, offset) in NEIGHBOURS.into_iter().enumerate() {
+            let Some(neighbour_slot) = self.slot(key + offset * length) else {
+                if neighbour == BELOW + 1 {
+                    for (x, z) in columns() {
+                        let node = (slot, local_index(IVec3::new(x, length - 1, z)));
+                        if !self.blocks(node) {
+                            self.set(node, LightChannel::Sky, VoxelLight::MAX);
+                            sky.push_back(node);
+                        }
+                    }
+                }
+                continue;
+            };
+
+            for local in face_voxels(-offset) {
+                let node = (neighbour_slot, local_index(local));
+                sky.push_back(node);
+                block.push_back(node);
+            }
+        }
+
+        self.add_light(LightChannel::Sky, sky);
+        self.add_light(LightChannel::Block, block);
+
+        // the chunk below was lit under the open sky, which the chunk may block.
+        if let Some(below) = self.slot(key - IVec3::Y * length) {
+            let mut removed = VecDeque::new();
+            for (x, z) in columns() {
+                let bottom = (slot, local_index(IVec3::new(x, 0, z)));
+                let top = (below, local_index(IVec3::new(x, length - 1, z)));
+                if self.get(bottom, LightChannel::Sky) < VoxelLight::MAX
+                    && self.get(top, LightChannel::Sky) == VoxelLight::MAX
+                {
+                    self.set(top, LightChannel::Sky, 0);
+                    removed.push_back((top, VoxelLight::MAX));
+                }
+            }
+
+            let relit = self.remove_light(LightChannel::Sky, removed);
+            self.add_light(LightChannel::Sky, relit);
+        }
+    }
+
+    /// Updates the light around a voxel after it was edited.
+    pub fn update_voxel(&mut self, pos: IVec3) {
+        let key = pos & !IVec3::splat(CHUNK_LENGTH as i32 - 1);
+        let Some(slot) = self.slot(key) else {
+            return;
+        };
+        let node = (slot, local_index(pos - key));
+
+        // the voxel is read again, the chunk may have been decompressed before the edit.
+        self.lit[slot].voxels.slice_mut()[node.1] =
+            self.chunks.voxel_at(pos).unwrap_or(Voxel::EMPTY_VOXEL);
+
+        for channel in [LightChannel::Sky, LightChannel::Block] {
+            let level = self.get(node, channel);
+            self.set(node, channel, 0);
+            let mut relit = self.remove_light(
+                channel,
+                [(node, level)]
+                    .into_iter()
+                    .filter(|(_, l)| *l > 0)
+                    .collect(),
+            );
+
+            // the light around the voxel spreads into it again, unless it blocks it.
+            relit.extend(
+                (0..NEIGHBOURS.len()).filter_map(|neighbour| self.neighbour(node, neighbour)),
+            );
+
+            let source = match channel {
+                LightChannel::Sky => {
+                    let under_open_sky = pos.y - key.y == CHUNK_LENGTH as i32 - 1
+                        && self.slot(key + IVec3::Y * CHUNK_LENGTH as i32).is_none();
+                    if under_open_sky && !self.blocks(node) {
+                        VoxelLight::MAX
+                    } else {
+                        0
+                    }
+                }
+                LightChannel::Block => self.emission(node),
+            };
+
+            if source > 0 {
+                self.set(node, channel, source);
+                relit.push_back(node);
+            }
+
+            self.add_light(channel, relit);
+        }
+    }
+
+    /// Writes the light of the touched chunks back and marks dirty the chunks whose mesh is lit differently.
+    pub fn finish(self, dirty_chunks: &mut DirtyChunks) {
+        for chunk in self.lit {
+            if !chunk.changed {
+                continue;
+            }
+
+            for (offset, dirty) in chunk.dirty_neighbours.into_iter().enumerate() {
+                if dirty {
+                    let offset =
+                        IVec3::new(offset as i32 % 3, offset as i32 / 3 % 3, offset as i32 / 9);
+                    dirty_chunks
+                        .mark_dirty(chunk.key + (offset - IVec3::ONE) * CHUNK_LENGTH as i32);
+                }
+            }
+
+            self.light.0.insert(chunk.key, chunk.light);
+        }
+    }
+
+    /// Returns the slot of the chunk with the specified key, decompressing it the first time it is touched.
+    fn slot(&mut self, key: IVec3) -> Option<usize> {
+        if let Some(slot) = self.slots.get(&key) {
+            return *slot;
+        }
+
+        let slot = match (self.chunks.buffer_at(key), self.light.buffer_at(key)) {
+            (Some(voxels), Some(light)) => {
+                self.lit.push(LitChunk {
+                    key,
+                    voxels: voxels.to_buffer(),
+                    light: light.to_buffer(),
+                    changed: false,
+                    dirty_neighbours: [false; 27],
+                });
+                Some(self.lit.len() - 1)
+            }
+            _ => None,
+        };
+
+        self.slots.insert(key, slot);
+        slot
+    }
+
+    /// Returns the voxel next to the specified one, `None` if it lies in a chunk which isn't lit.
+    #[inline]
+    fn neighbour(&mut self, (slot, index): LightNode, neighbour: usize) -> Option<LightNode> {
+        let local = ChunkShape::delinearize(index as u32);
+        let pos = IVec3::from_array(local.map(|x| x as i32)) + NEIGHBOURS[neighbour];
+
+        if pos.cmpge(IVec3::ZERO).all() && pos.cmplt(IVec3::splat(CHUNK_LENGTH as i32)).all() {
+            return Some((slot, local_index(pos)));
+        }
+
+        let key = self.lit[slot].key + NEIGHBOURS[neighbour] * CHUNK_LENGTH as i32;
+        let slot = self.slot(key)?;
+        // only the coordinate along the neighbour axis lies outside of the chunk, one voxel away from it.
+        let wrapped = pos & IVec3::splat(CHUNK_LENGTH as i32 - 1);
+        Some((slot, local_index(wrapped)))
+    }
+
+    #[inline]
+    fn get(&self, (slot, index): LightNode, channel: LightChannel) -> u8 {
+        channel.get(self.lit[slot].light.slice()[index])
+    }
+
+    #[inline]
+    fn set(&mut self, (slot, index): LightNode, channel: LightChannel, level: u8) {
+        let chunk = &mut self.lit[slot];
+        let light = &mut chunk.light.slice_mut()[index];
+        let updated = channel.with(*light, level);
+        if *light == updated {
+            return;
+        }
+
+        *light = updated;
+        chunk.changed = true;
+
+        // the voxels on the borders of the chunk pad the meshes of its neighbours.
+        let local = ChunkShape::delinearize(index as u32);
+        let [min, max] = [0, CHUNK_LENGTH - 1].map(|border| local.map(|x| (x == border) as usize));
+        for z in 1 - min[2]..=1 + max[2] {
+            for y in 1 - min[1]..=1 + max[1] {
+                for x in 1 - min[0]..=1 + max[0] {
+                    chunk.dirty_neighbours[x + y * 3 + z * 9] = true;
+                }
+            }
+        }
+    }
+
+    #[inline]
+    fn blocks(&self, (slot, index): LightNode) -> bool {
+        blocks_light(self.lit[slot].voxels.slice()[index], self.mesh_kinds)
+    }
+
+    /// Returns the level of the light emitted by a voxel.
+    #[inline]
+    fn emission(&self, (slot, index): LightNode) -> u8 {
+        let voxel = self.lit[slot].voxels.slice()[index];
+        if voxel.is_empty() {
+            return 0;
+        }
+
+        self.light_emission
+            .get(voxel.id as usize)
+            .map_or(0, |emission| (*emission).min(VoxelLight::MAX))
+    }
+
+    /// Spreads the light from the specified voxels to the voxels around them which don't block it.
+    fn add_light(&mut self, channel: LightChannel, mut queue: VecDeque<LightNode>) {
+        while let Some(node) = queue.pop_front() {
+            let level = self.get(node, channel);
+            if level <= 1 {
+                continue;
+            }
+
+            for neighbour in 0..NEIGHBOURS.len() {
+                let Some(next) = self.neighbour(node, neighbour) else {
+                    continue;
+                };
+
+                let spread = channel.spread(level, neighbour);
+                if !self.blocks(next) && self.get(next, channel) < spread {
+                    self.set(next, channel, spread);
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+
+    /// Darkens the voxels lit by the specified voxels, which were lit at the specified levels before being darkened.
+    /// Returns the voxels lit from elsewhere bordering the darkened ones, whose light spreads back into them.
+    fn remove_light(
+        &mut self,
+        channel: LightChannel,
+        mut queue: VecDeque<(LightNode, u8)>,
+    ) -> VecDeque<LightNode> {
+        let mut relit = VecDeque::new();
+
+        while let Some((node, level)) = queue.pop_front() {
+            for neighbour in 0..NEIGHBOURS.len() {
+                let Some(next) = self.neighbour(node, neighbour) else {
+                    continue;
+                };
+
+                let next_level = self.get(next, channel);
+                if next_level == 0 {
+                    continue;
+                }
+
+                if next_level < level || channel.spread(level, neighbour) == next_level {
+                    self.set(next, channel, 0);
+                    queue.push_back((next, next_level));
+
+                    // the light emitting voxels light themselves again.
+                    let emission = self.emission(next);
+                    if channel == LightChannel::Block && emission > 0 {
+                        self.set(next, channel, emission);
+                        relit.push_back(next);
+                    }
+                } else {
+                    relit.push_back(next);
+                }
+            }
+        }
+
+        relit
+    }
+}
+
+/// Returns the index of a voxel in a chunk from its position relative to the chunk minimum.
+#[inline]
+fn local_index(pos: IVec3) -> usize {
+    ChunkShape::linearize(pos.as_uvec3().to_array()) as usize
+}
+
+/// Returns the local X and Z coordinates of the columns of a chunk.
+fn columns() -> impl Iterator<Item = (i32, i32)> {
+    (0..CHUNK_LENGTH as i32).flat_map(|z| (0..CHUNK_LENGTH as i32).map(move |x| (x, z)))
+}
+
+/// Returns the voxels of a chunk lying on the face pointing towards the specified direction.
+fn face_voxels(direction: IVec3) -> impl Iterator<Item = IVec3> {
+    let border = direction.max(IVec3::ZERO) * (CHUNK_LENGTH as i32 - 1);
+    let fixed = direction.abs().cmpeq(IVec3::ONE);
+
+    columns().map(move |(u, v)| {
+        let free = if fixed.x {
+            IVec3::new(0, u, v)
+        } else if fixed.y {
+            IVec3::new(u, 0, v)
+        } else {
+            IVec3::new(u, v, 0)
+        };
+        IVec3::select(fixed, border, free)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STONE: Voxel = Voxel::new(1);
+    const LAMP: Voxel = Voxel::new(2);
+
+    const MESH_KINDS: [VoxelMeshKind; 3] = [VoxelMeshKind::Opaque; 3];
+    const LIGHT_EMISSION: [u8; 3] = [0, 0, 12];
+
+    /// Two by two columns of two chunks, with the ground a few voxels below the border between the chunks
+    /// and a roof covering part of it.
+    fn world() -> ChunkMap<Voxel, ChunkShape> {
+        let length = CHUNK_LENGTH as i32;
+        let mut chunks = ChunkMap::new(ChunkShape {});
+
+        for key in [0, length].into_iter().flat_map(|y| {
+            [0, length]
+                .into_iter()
+                .flat_map(move |z| [0, length].map(|x| IVec3::new(x, y, z)))
+        }) {
+            chunks.insert_empty(key);
+        }
+
+        for z in 0..2 * length {
+            for x in 0..2 * length {
+                for y in 0..length - 4 {
+                    *chunks.voxel_at_mut(IVec3::new(x, y, z)).unwrap() = STONE;
+                }
+            }
+        }
+
+        for z in 20..44 {
+            for x in 20..44 {
+                *chunks.voxel_at_mut(IVec3::new(x, length + 4, z)).unwrap() = STONE;
+            }
+        }
+
+        chunks
+    }
+
+    /// Lights every chunk from scratch, from the top.
+    fn light_from_scratch(chunks: &ChunkMap<Voxel, ChunkShape>) -> ChunkLightMap {
+        let mut light = ChunkLightMap::default();
+        let mut keys: Vec<IVec3> = chunks.iter_keys().collect();
+        keys.sort_unstable_by_key(|key| std::cmp::Reverse(key.y));
+
+        let mut update = LightUpdate::new(chunks, &mut light, &MESH_KINDS, &LIGHT_EMISSION);
+        keys.into_iter().for_each(|key| update.light_chunk(key));
+        update.finish(&mut DirtyChunks::default());
+        light
+    }
+
+    fn light_at(light: &ChunkLightMap, pos: IVec3) -> VoxelLight {
+        let key = pos & !IVec3::splat(CHUNK_LENGTH as i32 - 1);
+        unpack_light(
+            light
+                .buffer_at(key)
+                .unwrap()
+                .voxel_at((pos - key).as_uvec3()),
+        )
+    }
+
+    fn assert_same_light(chunks: &ChunkMap<Voxel, ChunkShape>, light: &ChunkLightMap) {
+        let expected = light_from_scratch(chunks);
+        for key in chunks.iter_keys() {
+            assert_eq!(
+                light.buffer_at(key).unwrap().to_buffer().slice(),
+                expected.buffer_at(key).unwrap().to_buffer().slice(),
+                "light of the chunk {key}"
+            );
+        }
+    }
+
+    #[test]
+    fn sunlight_goes_down_and_dims_under_the_roof() {
+        let chunks = world();
+        let light = light_from_scratch(&chunks);
+
+        assert_eq!(light_at(&light, IVec3::new(4, 28, 4)).sky, VoxelLight::MAX);
+        assert_eq!(light_at(&light, IVec3::new(4, 27, 4)).sky, 0);
+        // the middle of the roof is further away from its borders than the sunlight reaches.
+        assert_eq!(light_at(&light, IVec3::new(32, 28, 32)).sky, 3);
+        assert_eq!(
+            light_at(&light, IVec3::new(32, 40, 32)).sky,
+            VoxelLight::MAX
+        );
+    }
+
+    #[test]
+    fn edits_light_as_lighting_from_scratch() {
+        let mut chunks = world();
+        let mut light = light_from_scratch(&chunks);
+
+        let edits = [
+            // a lamp on the ground under the roof, across the border of four chunks.
+            (IVec3::new(32, 28, 32), LAMP),
+            // a hole through the roof, letting the sunlight in.
+            (IVec3::new(31, 36, 31), Voxel::EMPTY_VOXEL),
+            // a shaft dug below the hole, down to the chunks below.
+            (IVec3::new(31, 27, 31), Voxel::EMPTY_VOXEL),
+            (IVec3::new(31, 26, 31), Voxel::EMPTY_VOXEL),
+            // the hole closed again, and the lamp removed.
+            (IVec3::new(31, 36, 31), STONE),
+            (IVec3::new(32, 28, 32), Voxel::EMPTY_VOXEL),
+        ];
+
+        for (pos, voxel) in edits {
+            *chunks.voxel_at_mut(pos).unwrap() = voxel;
+
+            let mut update = LightUpdate::new(&chunks, &mut light, &MESH_KINDS, &LIGHT_EMISSION);
+            update.update_voxel(pos);
+            update.finish(&mut DirtyChunks::default());
+
+            assert_same_light(&chunks, &light);
+        }
+    }
+
+    #[test]
+    fn chunks_lit_from_below_match_chunks_lit_from_the_top() {
+        let chunks = world();
+        let mut light = ChunkLightMap::default();
+        let mut keys: Vec<IVec3> = chunks.iter_keys().collect();
+        keys.sort_unstable_by_key(|key| key.y);
+
+        for key in keys {
+            let mut update = LightUpdate::new(&chunks, &mut light, &MESH_KINDS, &LIGHT_EMISSION);
+            update.light_chunk(key);
+            update.finish(&mut DirtyChunks::default());
+        }
+
+        assert_same_light(&chunks, &light);
+    }
+}
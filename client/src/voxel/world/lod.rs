@@ -0,0 +1,114 @@
+use bevy::{
+    math::IVec3,
+    prelude::{
+        Component, DetectChanges, IntoSystemConfig, Local, OnUpdate, Plugin, Query, Res, ResMut,
+        Resource,
+    },
+};
+
+use super::{
+    chunks::{CurrentLocalPlayerChunk, DirtyChunks},
+    meshing::ChunkMeshingSet,
+    Chunk, Voxel, CHUNK_LENGTH,
+};
+use crate::GameState;
+
+/// The number of levels of detail chunks can be meshed at, each halving the resolution of the previous one.
+pub const CHUNK_LOD_LEVELS: usize = 4;
+
+/// Distances in chunks from the player up to which the chunks are meshed at each level of detail,
+/// starting from the full resolution. The chunks lying further away are meshed at the lowest level of detail.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChunkLodDistances(pub [i32; CHUNK_LOD_LEVELS - 1]);
+
+impl Default for ChunkLodDistances {
+    fn default() -> Self {
+        Self([6, 10, 14])
+    }
+}
+
+impl ChunkLodDistances {
+    /// Returns the level of detail of the chunk with the specified key, `0` being the full resolution.
+    pub fn lod_at(&self, player_chunk: IVec3, key: IVec3) -> u8 {
+        let distance = ((key - player_chunk) / CHUNK_LENGTH as i32)
+            .abs()
+            .max_element();
+
+        self.0
+            .iter()
+            .position(|max_distance| distance <= *max_distance)
+            .unwrap_or(CHUNK_LOD_LEVELS - 1) as u8
+    }
+}
+
+/// The level of detail a chunk was last meshed at.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChunkLod(pub u8);
+
+impl ChunkLod {
+    /// Returns how many voxels wide the voxels of the chunk are at this level of detail.
+    pub const fn scale(&self) -> u32 {
+        1 << self.0
+    }
+}
+
+/// Returns a voxel standing for the cube of `scale` voxels wide starting at the specified position.
+/// The cube is filled if at least half of its voxels are, with the material of its topmost voxel,
+/// which is the one seen from above.
+pub fn downsample_voxel(voxel_at: impl Fn(IVec3) -> Voxel, min: IVec3, scale: i32) -> Voxel {
+    let mut filled = 0;
+    let mut top = Voxel::EMPTY_VOXEL;
+
+    for y in (0..scale).rev() {
+        for z in 0..scale {
+            for x in 0..scale {
+                let voxel = voxel_at(min + IVec3::new(x, y, z));
+                if !voxel.is_empty() {
+                    filled += 1;
+                    if top.is_empty() {
+                        top = voxel;
+                    }
+                }
+            }
+        }
+    }
+
+    if filled * 2 >= scale.pow(3) {
+        top
+    } else {
+        Voxel::EMPTY_VOXEL
+    }
+}
+
+/// Meshes the chunks again whose level of detail changed as the player moved to another chunk.
+fn update_chunk_lods(
+    player_pos: Res<CurrentLocalPlayerChunk>,
+    lod_distances: Res<ChunkLodDistances>,
+    chunks: Query<(&Chunk, &ChunkLod)>,
+    mut dirty_chunks: ResMut<DirtyChunks>,
+    mut last_player_chunk: Local<Option<IVec3>>,
+) {
+    if *last_player_chunk == Some(player_pos.chunk_min) && !lod_distances.is_changed() {
+        return;
+    }
+    *last_player_chunk = Some(player_pos.chunk_min);
+
+    for (chunk, lod) in chunks.iter() {
+        if lod_distances.lod_at(player_pos.chunk_min, chunk.0) != lod.0 {
+            dirty_chunks.mark_dirty(chunk.0);
+        }
+    }
+}
+
+/// Meshes the distant chunks at a lower resolution.
+pub struct ChunkLodPlugin;
+
+impl Plugin for ChunkLodPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.init_resource::<ChunkLodDistances>().add_system(
+            update_chunk_lods
+                .in_set(OnUpdate(GameState::Game))
+                .before(ChunkMeshingSet),
+        );
+    }
+}
@@ -1,12 +1,18 @@
 use super::{
-    chunks::{ChunkEntities, ChunkLoadingSet, DirtyChunks},
+    chunks::{ChunkEntities, ChunkLoadingSet, CurrentLocalPlayerChunk, DirtyChunks},
+    light::{unpack_light, ChunkLightMap, LightUpdate},
+    lod::{downsample_voxel, ChunkLod, ChunkLodDistances, CHUNK_LOD_LEVELS},
     terrain::TerrainGenSet,
     Chunk, ChunkShape, Voxel, CHUNK_LENGTH,
 };
 use crate::{
     voxel::{
-        render::{mesh_buffer, ChunkMaterialSingleton, MeshBuffers},
-        storage::ChunkMap,
+        material::VoxelMaterialRegistry,
+        render::{
+            mesh_buffer, ChunkMaterialSingleton, MeshBuffers, TranslucentVoxelTerrainMesh,
+            VoxelBufferMeshes, VoxelLight, VoxelMeshKind,
+        },
+        storage::{ChunkMap, PaletteBuffer, VoxelBuffer},
     },
     GameState,
 };
@@ -16,13 +22,20 @@ use bevy::{
     tasks::{AsyncComputeTaskPool, Task},
 };
 
-use bevy_rapier3d::prelude::{AsyncCollider, ComputedColliderShape};
+use bevy_rapier3d::prelude::Collider;
 use futures_lite::future;
+use ilattice::{glam::UVec3, prelude::Extent};
+use ndshape::RuntimeShape;
 use once_cell::sync::Lazy;
-use std::cell::RefCell;
+use std::{cell::RefCell, sync::Arc};
 use thread_local::ThreadLocal;
 
+/// The mesh of the translucent voxels of a chunk, drawn by a child entity of the chunk.
+#[derive(Component)]
+pub struct ChunkTranslucentMesh(Handle<Mesh>);
+
 /// Attaches to the newly inserted chunk entities components required for rendering.
+/// The translucent voxels are drawn by a child entity, so they are blended in a separate pass.
 pub fn prepare_chunks(
     chunks: Query<(Entity, &Chunk), Added<Chunk>>,
     mut meshes: ResMut<Assets<Mesh>>,
@@ -30,16 +43,74 @@ pub fn prepare_chunks(
     mut cmds: Commands,
 ) {
     for (chunk, chunk_key) in chunks.iter() {
-        cmds.entity(chunk).insert((
-            MaterialMeshBundle {
-                material: (**material).clone(),
-                mesh: meshes.add(Mesh::new(PrimitiveTopology::TriangleList)),
-                transform: Transform::from_translation(chunk_key.0.as_vec3()),
-                visibility: Visibility::Hidden,
-                ..Default::default()
-            },
-            Aabb::from_min_max(Vec3::ZERO, Vec3::splat(CHUNK_LENGTH as f32)),
-        ));
+        let translucent_mesh = meshes.add(Mesh::new(PrimitiveTopology::TriangleList));
+
+        cmds.entity(chunk)
+            .insert((
+                MaterialMeshBundle {
+                    material: material.opaque.clone(),
+                    mesh: meshes.add(Mesh::new(PrimitiveTopology::TriangleList)),
+                    transform: Transform::from_translation(chunk_key.0.as_vec3()),
+                    visibility: Visibility::Hidden,
+                    ..Default::default()
+                },
+                Aabb::from_min_max(Vec3::ZERO, Vec3::splat(CHUNK_LENGTH as f32)),
+                ChunkTranslucentMesh(translucent_mesh.clone()),
+            ))
+            .with_children(|parent| {
+                parent.spawn((
+                    MaterialMeshBundle {
+                        material: material.translucent.clone(),
+                        mesh: translucent_mesh,
+                        ..Default::default()
+                    },
+                    Aabb::from_min_max(Vec3::ZERO, Vec3::splat(CHUNK_LENGTH as f32)),
+                    TranslucentVoxelTerrainMesh,
+                ));
+            });
+    }
+}
+
+/// How the voxels of each material id are meshed and lit, shared with the meshing tasks.
+#[derive(Resource, Default, Clone)]
+struct VoxelMeshMaterials {
+    kinds: Arc<[VoxelMeshKind]>,
+    /// The level of the light emitted by the voxels of each material.
+    light_emission: Arc<[u8]>,
+}
+
+/// Looks up how the voxels of each material are meshed from the material flags and the light they emit
+/// from the material emissive colour, and lights and meshes the loaded chunks again if any of them changed.
+fn update_voxel_mesh_materials(
+    registry: Res<VoxelMaterialRegistry>,
+    mut mesh_materials: ResMut<VoxelMeshMaterials>,
+    chunk_entities: Res<ChunkEntities>,
+    mut dirty_chunks: ResMut<DirtyChunks>,
+    mut light: ResMut<ChunkLightMap>,
+) {
+    let kinds: Arc<[VoxelMeshKind]> = registry
+        .iter_mats()
+        .map(|mat| VoxelMeshKind::from_flags(mat.flags))
+        .collect();
+
+    let light_emission: Arc<[u8]> = registry
+        .iter_mats()
+        .map(|mat| {
+            let emissive = mat.emissive.r().max(mat.emissive.g()).max(mat.emissive.b());
+            (emissive.clamp(0.0, 1.0) * VoxelLight::MAX as f32).round() as u8
+        })
+        .collect();
+
+    if kinds != mesh_materials.kinds || light_emission != mesh_materials.light_emission {
+        *mesh_materials = VoxelMeshMaterials {
+            kinds,
+            light_emission,
+        };
+        // the voxels may block or emit light differently, so the chunks are lit again from scratch.
+        light.clear();
+        chunk_entities
+            .iter_keys()
+            .for_each(|key| dirty_chunks.mark_dirty(*key));
     }
 }
 
@@ -47,12 +118,134 @@ pub fn prepare_chunks(
 static SHARED_MESH_BUFFERS: Lazy<ThreadLocal<RefCell<MeshBuffers<Voxel, ChunkShape>>>> =
     Lazy::new(ThreadLocal::default);
 
+// a pool of mesh buffers for each level of detail below the full resolution.
+#[allow(clippy::type_complexity)]
+static SHARED_LOD_MESH_BUFFERS: Lazy<
+    ThreadLocal<RefCell<Vec<MeshBuffers<Voxel, RuntimeShape<u32, 3>>>>>,
+> = Lazy::new(ThreadLocal::default);
+
+/// The voxel data of a chunk along the 26 chunks around it, whose borders pad the chunk when meshing it,
+/// with the light of the same chunks when the chunk is lit.
+struct ChunkNeighbourhood {
+    voxels: [Option<PaletteBuffer<Voxel, ChunkShape>>; 27],
+    light: Option<[Option<PaletteBuffer<u8, ChunkShape>>; 27]>,
+}
+
+impl ChunkNeighbourhood {
+    const CENTER: usize = 13;
+
+    /// Gathers the voxel data around the specified chunk, returns `None` if the chunk itself isn't loaded.
+    fn new(chunks: &ChunkMap<Voxel, ChunkShape>, key: IVec3) -> Option<Self> {
+        chunks.buffer_at(key)?;
+
+        Some(Self {
+            voxels: std::array::from_fn(|index| {
+                chunks
+                    .buffer_at(key + Self::offset(index) * CHUNK_LENGTH as i32)
+                    .cloned()
+            }),
+            light: None,
+        })
+    }
+
+    /// Gathers the light around the specified chunk along its voxel data.
+    fn with_light(mut self, light: &ChunkLightMap, key: IVec3) -> Self {
+        self.light = Some(std::array::from_fn(|index| {
+            light
+                .buffer_at(key + Self::offset(index) * CHUNK_LENGTH as i32)
+                .cloned()
+        }));
+        self
+    }
+
+    /// Returns the offset in chunks of the neighbour at the specified index.
+    fn offset(index: usize) -> IVec3 {
+        IVec3::new(index as i32 % 3, index as i32 / 3 % 3, index as i32 / 9) - IVec3::ONE
+    }
+
+    fn center(&self) -> &PaletteBuffer<Voxel, ChunkShape> {
+        self.voxels[Self::CENTER].as_ref().unwrap()
+    }
+
+    /// Returns the index of the chunk holding the specified position relative to the minimum of the center chunk,
+    /// along the position relative to the minimum of that chunk.
+    fn locate(pos: IVec3) -> (usize, UVec3) {
+        let length = CHUNK_LENGTH as i32;
+        let offset = IVec3::from_array(pos.to_array().map(|x| x.div_euclid(length))) + IVec3::ONE;
+        let local = pos.to_array().map(|x| x.rem_euclid(length) as u32);
+
+        (
+            (offset.x + offset.y * 3 + offset.z * 9) as usize,
+            local.into(),
+        )
+    }
+
+    /// Returns the voxel at the specified position relative to the minimum of the center chunk,
+    /// the voxels of the neighbours which aren't loaded are empty.
+    fn voxel_at(&self, pos: IVec3) -> Voxel {
+        let (index, local) = Self::locate(pos);
+
+        self.voxels
+            .get(index)
+            .and_then(Option::as_ref)
+            .map_or(Voxel::EMPTY_VOXEL, |buffer| buffer.voxel_at(local))
+    }
+
+    /// Returns the light at the specified position relative to the minimum of the center chunk,
+    /// the voxels of the neighbours which aren't lit stand in the sunlight.
+    fn light_at(&self, pos: IVec3) -> VoxelLight {
+        let (index, local) = Self::locate(pos);
+
+        self.light
+            .as_ref()
+            .and_then(|light| light.get(index))
+            .and_then(Option::as_ref)
+            .map_or(VoxelLight::SKY, |buffer| {
+                unpack_light(buffer.voxel_at(local))
+            })
+    }
+}
+
+/// Lights the chunks loaded during the frame and updates the light around the voxels edited during the frame,
+/// then marks dirty the chunks whose light changed.
+fn update_chunk_light(
+    mut dirty_chunks: ResMut<DirtyChunks>,
+    chunks: Res<ChunkMap<Voxel, ChunkShape>>,
+    mut light: ResMut<ChunkLightMap>,
+    mesh_materials: Res<VoxelMeshMaterials>,
+) {
+    let mut loaded: Vec<IVec3> = dirty_chunks
+        .iter_dirty()
+        .filter(|key| chunks.exists(**key) && !light.is_lit(**key))
+        .copied()
+        .collect();
+    let edited: Vec<IVec3> = dirty_chunks.iter_edited().copied().collect();
+
+    // the chunks are lit from the top, so the sunlight reaches the chunks below before they are lit.
+    loaded.sort_unstable_by_key(|key| std::cmp::Reverse(key.y));
+
+    let mut update = LightUpdate::new(
+        &chunks,
+        &mut light,
+        &mesh_materials.kinds,
+        &mesh_materials.light_emission,
+    );
+    loaded.into_iter().for_each(|key| update.light_chunk(key));
+    edited.into_iter().for_each(|pos| update.update_voxel(pos));
+    update.finish(&mut dirty_chunks);
+}
+
 /// Queues meshing tasks for the chunks in need of a remesh.
+#[allow(clippy::too_many_arguments)]
 fn queue_mesh_tasks(
     mut commands: Commands,
     dirty_chunks: Res<DirtyChunks>,
     chunk_entities: Res<ChunkEntities>,
     chunks: Res<ChunkMap<Voxel, ChunkShape>>,
+    light: Res<ChunkLightMap>,
+    mesh_materials: Res<VoxelMeshMaterials>,
+    player_pos: Res<CurrentLocalPlayerChunk>,
+    lod_distances: Res<ChunkLodDistances>,
 ) {
     let task_pool = AsyncComputeTaskPool::get();
 
@@ -60,50 +253,156 @@ fn queue_mesh_tasks(
         .iter_dirty()
         .filter_map(|key| chunk_entities.entity(*key).map(|entity| (key, entity)))
         .filter_map(|(key, entity)| {
-            chunks
-                .buffer_at(*key)
-                .map(|buffer| (buffer.clone(), entity))
+            let lod = ChunkLod(lod_distances.lod_at(player_pos.chunk_min, *key));
+            let neighbourhood = ChunkNeighbourhood::new(&chunks, *key)?;
+            // only the chunks meshed at the full resolution are lit.
+            let neighbourhood = if lod.0 == 0 {
+                neighbourhood.with_light(&light, *key)
+            } else {
+                neighbourhood
+            };
+
+            Some((entity, lod, neighbourhood))
         })
-        .map(|(buffer, entity)| {
+        .map(|(entity, lod, neighbourhood)| {
+            let mesh_materials = mesh_materials.clone();
             (
                 entity,
+                lod,
                 ChunkMeshingTask(task_pool.spawn(async move {
-                    let mut mesh_buffers = SHARED_MESH_BUFFERS
-                        .get_or(|| {
-                            RefCell::new(MeshBuffers::<Voxel, ChunkShape>::new(ChunkShape {}))
-                        })
-                        .borrow_mut();
+                    let mut meshes = VoxelBufferMeshes::default();
+                    mesh_chunk(&neighbourhood, lod, &mesh_materials.kinds, &mut meshes);
 
-                    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
-                    mesh_buffer(&buffer, &mut mesh_buffers, &mut mesh, 1.0);
+                    let collider = (!meshes.collider_indices.is_empty()).then(|| {
+                        Collider::trimesh(
+                            std::mem::take(&mut meshes.collider_vertices),
+                            std::mem::take(&mut meshes.collider_indices),
+                        )
+                    });
 
-                    mesh
+                    ChunkMeshingOutput {
+                        opaque: meshes.opaque,
+                        translucent: meshes.translucent,
+                        collider,
+                    }
                 })),
             )
         })
-        .for_each(|(entity, task)| {
-            commands.entity(entity).insert(task);
+        .for_each(|(entity, lod, task)| {
+            commands.entity(entity).insert((lod, task));
         });
 }
 
-/// Polls and process the generated chunk meshes
+/// Meshes a chunk at the specified level of detail, downsampling its voxels below the full resolution.
+/// Only the chunks meshed at the full resolution are lit, the distant ones stand in the sunlight.
+/// The collider of the chunk is always built at the full resolution, so the bodies moving across the distant chunks
+/// don't fall through them.
+fn mesh_chunk(
+    neighbourhood: &ChunkNeighbourhood,
+    lod: ChunkLod,
+    mesh_kinds: &[VoxelMeshKind],
+    meshes: &mut VoxelBufferMeshes,
+) {
+    let center = neighbourhood.center();
+    let mut mesh_buffers = SHARED_MESH_BUFFERS
+        .get_or(|| RefCell::new(MeshBuffers::<Voxel, ChunkShape>::new(ChunkShape {})))
+        .borrow_mut();
+
+    if lod.0 == 0 {
+        mesh_buffer(
+            &center.to_buffer(),
+            |pos| neighbourhood.voxel_at(pos),
+            |pos| neighbourhood.light_at(pos),
+            &mut mesh_buffers,
+            mesh_kinds,
+            meshes,
+            1.0,
+        );
+        return;
+    }
+
+    let mut full_resolution = VoxelBufferMeshes::default();
+    mesh_buffer(
+        &center.to_buffer(),
+        |pos| neighbourhood.voxel_at(pos),
+        |_| VoxelLight::SKY,
+        &mut mesh_buffers,
+        mesh_kinds,
+        &mut full_resolution,
+        1.0,
+    );
+
+    let scale = lod.scale();
+    let mut lod_mesh_buffers = SHARED_LOD_MESH_BUFFERS
+        .get_or(|| {
+            RefCell::new(
+                (1..CHUNK_LOD_LEVELS)
+                    .map(|lod| {
+                        MeshBuffers::new(RuntimeShape::<u32, 3>::new([CHUNK_LENGTH >> lod; 3]))
+                    })
+                    .collect(),
+            )
+        })
+        .borrow_mut();
+
+    let downsample = |pos: IVec3| {
+        downsample_voxel(
+            |pos| neighbourhood.voxel_at(pos),
+            pos * scale as i32,
+            scale as i32,
+        )
+    };
+
+    let mut buffer = VoxelBuffer::<Voxel, RuntimeShape<u32, 3>>::new_empty(
+        RuntimeShape::<u32, 3>::new([CHUNK_LENGTH / scale; 3]),
+    );
+    Extent::from_min_and_shape(UVec3::ZERO, UVec3::splat(CHUNK_LENGTH / scale))
+        .iter3()
+        .for_each(|pos| *buffer.voxel_at_mut(pos) = downsample(pos.as_ivec3().to_array().into()));
+
+    mesh_buffer(
+        &buffer,
+        downsample,
+        |_| VoxelLight::SKY,
+        &mut lod_mesh_buffers[lod.0 as usize - 1],
+        mesh_kinds,
+        meshes,
+        scale as f32,
+    );
+
+    meshes.collider_vertices = full_resolution.collider_vertices;
+    meshes.collider_indices = full_resolution.collider_indices;
+}
+
+/// Polls and process the generated chunk meshes.
 fn process_mesh_tasks(
     mut meshes: ResMut<Assets<Mesh>>,
-    mut chunk_query: Query<(Entity, &Handle<Mesh>, &mut ChunkMeshingTask), With<Chunk>>,
+    mut chunk_query: Query<(
+        Entity,
+        &Handle<Mesh>,
+        &ChunkTranslucentMesh,
+        &mut ChunkMeshingTask,
+    )>,
     mut commands: Commands,
 ) {
-    chunk_query.for_each_mut(|(entity, handle, mut mesh_task)| {
-        if let Some(mesh) = future::block_on(future::poll_once(&mut mesh_task.0)) {
-            let indices = mesh.indices().unwrap();
-
-            if !indices.is_empty() {
-                commands.entity(entity).insert((
-                    AsyncCollider(ComputedColliderShape::TriMesh),
-                    RapierSlowdownWorkaround,
-                ));
+    chunk_query.for_each_mut(|(entity, handle, translucent_handle, mut mesh_task)| {
+        if let Some(ChunkMeshingOutput {
+            opaque: mesh,
+            translucent: translucent_mesh,
+            collider,
+        }) = future::block_on(future::poll_once(&mut mesh_task.0))
+        {
+            if let Some(collider) = collider {
+                commands
+                    .entity(entity)
+                    .insert((collider, RapierSlowdownWorkaround));
+            } else {
+                // the chunk may have been emptied by an edit, drop its outdated collider.
+                commands.entity(entity).remove::<Collider>();
             }
 
             *meshes.get_mut(handle).unwrap() = mesh;
+            *meshes.get_mut(&translucent_handle.0).unwrap() = translucent_mesh;
             commands.entity(entity).remove::<ChunkMeshingTask>();
         }
     });
@@ -124,9 +423,13 @@ impl Plugin for VoxelWorldMeshingPlugin {
                 .after(TerrainGenSet)
                 .after(ChunkLoadingSet),
         )
+        .init_resource::<VoxelMeshMaterials>()
+        .init_resource::<ChunkLightMap>()
         .add_systems(
             (
                 prepare_chunks,
+                update_voxel_mesh_materials.run_if(resource_changed::<VoxelMaterialRegistry>()),
+                update_chunk_light,
                 queue_mesh_tasks,
                 process_mesh_tasks,
                 rapier_slowdown_workaround,
@@ -137,8 +440,15 @@ impl Plugin for VoxelWorldMeshingPlugin {
     }
 }
 
+/// The meshes of a chunk along its collider.
+pub struct ChunkMeshingOutput {
+    opaque: Mesh,
+    translucent: Mesh,
+    collider: Option<Collider>,
+}
+
 #[derive(Component)]
-pub struct ChunkMeshingTask(Task<Mesh>);
+pub struct ChunkMeshingTask(Task<ChunkMeshingOutput>);
 
 #[derive(Component)]
 #[component(storage = "SparseSet")]
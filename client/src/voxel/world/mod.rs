@@ -2,21 +2,30 @@ use bevy::{
     math::IVec3,
     prelude::{Component, Plugin},
 };
-use ndshape::ConstShape3u32;
 
-use super::{storage::ChunkMap, terraingen, Voxel};
+use super::{
+    storage::{ChunkMap, RegionStore},
+    terraingen, Voxel,
+};
 
 /// Systems for dynamically loading / unloading regions (aka chunks) of the world according to camera position.
 mod chunks;
 pub use chunks::{
-    ChunkCommandQueue, ChunkEntities, ChunkLoadRadius, CurrentLocalPlayerChunk, DirtyChunks,
+    ChunkCommandQueue, ChunkEntities, ChunkLoadRadius, ChunkUnloaded, CurrentLocalPlayerChunk,
+    DirtyChunks,
 };
 
 mod chunks_anim;
-pub mod materials;
+mod fog;
+pub use fog::{DistanceFog, DistanceFogFalloff};
+mod gravity;
+mod light;
+mod lod;
 mod meshing;
+pub use meshing::ChunkMeshingSet;
 mod sky;
 mod terrain;
+pub use terrain::{TerrainGenMode, TerrainGenTask};
 
 /// Registers all resources and systems for simulating and rendering an editable and interactive voxel world.
 pub struct VoxelWorldPlugin;
@@ -24,23 +33,27 @@ pub struct VoxelWorldPlugin;
 impl Plugin for VoxelWorldPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
         app.insert_resource(ChunkMap::<Voxel, ChunkShape>::new(ChunkShape {}))
+            .insert_resource(RegionStore::new(WORLD_SAVE_DIR))
             .add_plugin(chunks::VoxelWorldChunkingPlugin)
             .add_plugin(meshing::VoxelWorldMeshingPlugin)
+            .add_plugin(lod::ChunkLodPlugin)
+            .add_plugin(gravity::VoxelGravityPlugin)
             // ordering of plugin insertion matters here.
             .add_plugin(terraingen::TerrainGeneratorPlugin)
             .add_plugin(terrain::VoxelWorldTerrainGenPlugin)
             .add_plugin(super::material::VoxelMaterialPlugin)
             .add_plugin(super::render::ChunkMaterialPlugin)
-            .add_plugin(materials::VoxelWorldBaseMaterialsPlugin)
+            .add_plugin(fog::DistanceFogPlugin)
             .add_plugin(chunks_anim::ChunkAppearanceAnimatorPlugin)
             //.add_plugin(bevy_atmosphere::plugin::AtmospherePlugin)
             .add_plugin(sky::InteractiveSkyboxPlugin);
     }
 }
 
-pub const CHUNK_LENGTH: u32 = 32;
-pub const CHUNK_LENGTH_U: usize = CHUNK_LENGTH as usize;
-pub type ChunkShape = ConstShape3u32<CHUNK_LENGTH, CHUNK_LENGTH, CHUNK_LENGTH>;
+/// Directory where the region files of the worlds are saved, in a subdirectory per seed.
+pub const WORLD_SAVE_DIR: &str = "saves/world";
+
+pub use common::{voxel::ChunkShape, CHUNK_LENGTH};
 
 // A component tagging an entity as a chunk.
 #[derive(Component)]
@@ -1,10 +1,32 @@
 use bevy::prelude::{
-    in_state, Color, Commands, Deref, DirectionalLight, DirectionalLightBundle, Entity,
-    IntoSystemAppConfig, IntoSystemConfig, OnEnter, ParamSet, Plugin, Query, Res, Resource,
-    Transform, Vec3, With,
+    in_state, AmbientLight, ClearColor, Color, Commands, Deref, DirectionalLight,
+    DirectionalLightBundle, Entity, IntoSystemAppConfig, IntoSystemConfigs, OnEnter, ParamSet,
+    Plugin, Query, Res, ResMut, Resource, Time, Transform, Vec3, With,
 };
+use common::time_of_day::TimeOfDay;
 
-use crate::{voxel::player::CameraMode, GameState};
+use crate::{
+    voxel::{player::CameraMode, DistanceFog},
+    GameState,
+};
+
+/// Illuminance of the sunlight at noon, matching the default of [`DirectionalLight`].
+const SUN_ILLUMINANCE: f32 = 100_000.0;
+/// Illuminance of the moonlight.
+const MOON_ILLUMINANCE: f32 = 8_000.0;
+
+const SUN_COLOR: Color = Color::rgb(1.0, 0.98, 0.92);
+/// Colour of the sunlight when the sun is low, at sunrise and sunset.
+const SUNSET_COLOR: Color = Color::rgb(1.0, 0.58, 0.32);
+const MOON_COLOR: Color = Color::rgb(0.62, 0.7, 1.0);
+
+const DAY_SKY_COLOR: Color = Color::rgb(0.55, 0.74, 0.93);
+const SUNSET_SKY_COLOR: Color = Color::rgb(0.85, 0.52, 0.38);
+const NIGHT_SKY_COLOR: Color = Color::rgb(0.02, 0.03, 0.08);
+
+/// Brightness of the ambient light at noon and at midnight.
+const DAY_AMBIENT_BRIGHTNESS: f32 = 1.0;
+const NIGHT_AMBIENT_BRIGHTNESS: f32 = 0.12;
 
 #[derive(Resource, Deref)]
 struct SkyLightEntity(Entity);
@@ -44,11 +66,95 @@ fn update_light_position(
     }
 }
 
+/// Advances the time of day between the updates received from the server.
+fn advance_time_of_day(time: Res<Time>, mut time_of_day: ResMut<TimeOfDay>) {
+    time_of_day.advance(time.delta_seconds());
+}
+
+/// Linearly interpolates between two colours.
+fn mix_color(from: Color, to: Color, t: f32) -> Color {
+    let (from, to) = (
+        Vec3::from_slice(&from.as_rgba_f32()),
+        Vec3::from_slice(&to.as_rgba_f32()),
+    );
+    let mixed = from.lerp(to, t.clamp(0.0, 1.0));
+    Color::rgb(mixed.x, mixed.y, mixed.z)
+}
+
+/// Returns the colour of the sky, which the terrain fades into in the distance.
+fn sky_color(time_of_day: &TimeOfDay) -> Color {
+    let daylight = time_of_day.daylight();
+    // the sky turns orange while the sun is close to the horizon.
+    let sunset = 1.0 - (time_of_day.sun_direction().y.abs() * 4.0).min(1.0);
+
+    mix_color(
+        mix_color(NIGHT_SKY_COLOR, DAY_SKY_COLOR, daylight),
+        SUNSET_SKY_COLOR,
+        sunset * daylight.max(0.25),
+    )
+}
+
+/// Moves the sky light along the sun or the moon, whichever is up, and dims the lights and the sky as the night falls.
+fn update_sky_lighting(
+    time_of_day: Res<TimeOfDay>,
+    sky_light_entity: Res<SkyLightEntity>,
+    mut lights: Query<(&mut Transform, &mut DirectionalLight)>,
+    mut ambient_light: ResMut<AmbientLight>,
+    mut clear_color: ResMut<ClearColor>,
+) {
+    let Ok((mut transform, mut light)) = lights.get_mut(**sky_light_entity) else {
+        return;
+    };
+
+    let daylight = time_of_day.daylight();
+    let sun = time_of_day.sun_direction();
+    let celestial_body = if sun.y >= 0.0 {
+        sun
+    } else {
+        time_of_day.moon_direction()
+    };
+
+    transform.look_to(-celestial_body, Vec3::Y);
+    light.illuminance = MOON_ILLUMINANCE + (SUN_ILLUMINANCE - MOON_ILLUMINANCE) * daylight;
+    light.color = mix_color(
+        MOON_COLOR,
+        mix_color(SUNSET_COLOR, SUN_COLOR, sun.y * 3.0),
+        daylight,
+    );
+
+    ambient_light.brightness =
+        NIGHT_AMBIENT_BRIGHTNESS + (DAY_AMBIENT_BRIGHTNESS - NIGHT_AMBIENT_BRIGHTNESS) * daylight;
+    ambient_light.color = mix_color(MOON_COLOR, Color::WHITE, daylight);
+
+    clear_color.0 = sky_color(&time_of_day);
+}
+
+/// Fades the distant meshes into the colour of the sky.
+/// The fog is only updated once the colour changed noticeably, as updating it uploads the terrain materials again.
+fn update_fog_color(time_of_day: Res<TimeOfDay>, mut fog: ResMut<DistanceFog>) {
+    let quantize = |color: Color| color.as_rgba_f32().map(|c| (c * 255.0).round() as u8);
+    let color = sky_color(&time_of_day);
+
+    if quantize(fog.color) != quantize(color) {
+        fog.color = color;
+    }
+}
+
 pub struct InteractiveSkyboxPlugin;
 
 impl Plugin for InteractiveSkyboxPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
-        app.add_system(setup_sky_lighting.in_schedule(OnEnter(GameState::Game)))
-            .add_system(update_light_position.run_if(in_state(GameState::Game)));
+        app.init_resource::<TimeOfDay>()
+            .add_system(setup_sky_lighting.in_schedule(OnEnter(GameState::Game)))
+            .add_systems(
+                (
+                    advance_time_of_day,
+                    update_light_position,
+                    update_sky_lighting,
+                    update_fog_color,
+                )
+                    .chain()
+                    .distributive_run_if(in_state(GameState::Game)),
+            );
     }
 }
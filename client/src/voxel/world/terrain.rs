@@ -1,42 +1,72 @@
 use super::{
     chunks::{ChunkLoadingSet, DirtyChunks},
-    Chunk, ChunkShape,
+    Chunk, ChunkShape, WORLD_SAVE_DIR,
 };
 use crate::{
     voxel::{
-        storage::{ChunkMap, VoxelBuffer},
-        terraingen::TERRAIN_GENERATOR,
+        storage::{ChunkMap, PaletteBuffer, RegionStore, VoxelBuffer},
+        terraingen::{
+            biome_definitions_loaded, water::SeaLevel, BiomeDefinitionsReloaded, WorldSeed,
+            TERRAIN_GENERATOR, TERRAIN_MAX_HEIGHT,
+        },
         Voxel,
     },
     GameState,
 };
 use bevy::{
     prelude::{
-        Added, Commands, Component, Entity, IntoSystemConfigs, IntoSystemSetConfig, OnUpdate,
-        Plugin, Query, ResMut, SystemSet,
+        resource_added, resource_changed, resource_equals, resource_exists, Added, Commands,
+        Component, Entity, EventReader, IntoSystemConfig, IntoSystemConfigs, IntoSystemSetConfig,
+        OnUpdate, Plugin, Query, Res, ResMut, Resource, SystemSet,
     },
     tasks::{AsyncComputeTaskPool, Task},
 };
 use futures_lite::future;
+use std::path::Path;
+
+/// Seeds the terrain generator with the seed sent by the server and switches to the region files matching it.
+fn apply_world_seed(seed: Res<WorldSeed>, mut region_store: ResMut<RegionStore>) {
+    TERRAIN_GENERATOR.write().unwrap().set_seed(*seed);
+    *region_store = RegionStore::new(Path::new(WORLD_SAVE_DIR).join(seed.0.to_string()));
+}
+
+/// Floods the terrain up to the sea level sent by the server.
+fn apply_sea_level(sea_level: Res<SeaLevel>) {
+    TERRAIN_GENERATOR
+        .write()
+        .unwrap()
+        .set_sea_level(sea_level.0);
+}
 
 /// Queues the terrain gen async tasks for the newly created chunks.
-fn queue_terrain_gen(mut commands: Commands, new_chunks: Query<(Entity, &Chunk), Added<Chunk>>) {
+/// Chunks previously saved to disk are loaded back instead of being generated.
+/// Nothing is generated before the server sent the world seed and the biome definitions are loaded.
+fn queue_terrain_gen(
+    mut commands: Commands,
+    new_chunks: Query<(Entity, &Chunk), Added<Chunk>>,
+    region_store: Res<RegionStore>,
+) {
     let task_pool = AsyncComputeTaskPool::get();
 
     new_chunks
         .iter()
-        .filter(|(_, key)| key.0.y < 288)
+        .filter(|(_, key)| key.0.y < TERRAIN_MAX_HEIGHT)
         .map(|(entity, key)| (entity, key.0))
         .map(|(entity, key)| {
+            let region_store = region_store.clone();
             (
                 entity,
                 (TerrainGenTask(task_pool.spawn(async move {
+                    if let Some(chunk_data) = region_store.load(key) {
+                        return chunk_data;
+                    }
+
                     let mut chunk_data = VoxelBuffer::<Voxel, ChunkShape>::new_empty(ChunkShape {});
                     TERRAIN_GENERATOR
                         .read()
                         .unwrap()
                         .generate(key, &mut chunk_data);
-                    chunk_data
+                    PaletteBuffer::from(chunk_data)
                 }))),
             )
         })
@@ -45,6 +75,38 @@ fn queue_terrain_gen(mut commands: Commands, new_chunks: Query<(Entity, &Chunk),
         });
 }
 
+/// Generates the loaded chunks again after the biome definitions were hot reloaded, discarding their saved data,
+/// so the edits made to the definition files show up in game.
+/// The voxel edits logged by the server are requested again once the chunks are generated.
+fn regenerate_chunks_on_biome_reload(
+    mut commands: Commands,
+    mut reloaded: EventReader<BiomeDefinitionsReloaded>,
+    chunks: Query<(Entity, &Chunk)>,
+) {
+    if reloaded.iter().count() == 0 {
+        return;
+    }
+
+    let task_pool = AsyncComputeTaskPool::get();
+
+    chunks
+        .iter()
+        .filter(|(_, key)| key.0.y < TERRAIN_MAX_HEIGHT)
+        .for_each(|(entity, key)| {
+            let key = key.0;
+            commands
+                .entity(entity)
+                .insert(TerrainGenTask(task_pool.spawn(async move {
+                    let mut chunk_data = VoxelBuffer::<Voxel, ChunkShape>::new_empty(ChunkShape {});
+                    TERRAIN_GENERATOR
+                        .read()
+                        .unwrap()
+                        .generate(key, &mut chunk_data);
+                    PaletteBuffer::from(chunk_data)
+                })));
+        });
+}
+
 /// Polls for finished gen tasks and put back the generated terrain into the voxel map
 pub fn process_terrain_gen(
     mut chunk_data: ResMut<ChunkMap<Voxel, ChunkShape>>,
@@ -54,13 +116,23 @@ pub fn process_terrain_gen(
 ) {
     gen_chunks.for_each_mut(|(entity, chunk, mut gen_task)| {
         if let Some(data) = future::block_on(future::poll_once(&mut gen_task.0)) {
+            dirty_chunks.mark_loaded(chunk.0, &data);
             chunk_data.insert(chunk.0, data);
-            dirty_chunks.mark_dirty(chunk.0);
             commands.entity(entity).remove::<TerrainGenTask>();
         }
     });
 }
 
+/// Where the voxel data of the newly created chunks comes from.
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TerrainGenMode {
+    /// Chunks are loaded from disk or generated locally.
+    #[default]
+    Local,
+    /// Chunks are streamed by the server, see [`crate::voxel::networking::stream`].
+    Remote,
+}
+
 /// Handles terrain generation.
 pub struct VoxelWorldTerrainGenPlugin;
 
@@ -70,18 +142,30 @@ pub struct TerrainGenSet;
 
 impl Plugin for VoxelWorldTerrainGenPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
-        app.configure_set(
-            TerrainGenSet
-                .in_set(OnUpdate(GameState::Game))
-                .after(ChunkLoadingSet),
-        )
-        .add_systems(
-            (queue_terrain_gen, process_terrain_gen)
-                .chain()
-                .in_set(TerrainGenSet),
-        );
+        app.init_resource::<TerrainGenMode>()
+            .init_resource::<SeaLevel>()
+            .configure_set(
+                TerrainGenSet
+                    .in_set(OnUpdate(GameState::Game))
+                    .after(ChunkLoadingSet),
+            )
+            .add_systems(
+                (
+                    apply_world_seed.run_if(resource_added::<WorldSeed>()),
+                    apply_sea_level.run_if(resource_changed::<SeaLevel>()),
+                    queue_terrain_gen
+                        .run_if(resource_exists::<WorldSeed>())
+                        .run_if(biome_definitions_loaded)
+                        .run_if(resource_equals(TerrainGenMode::Local)),
+                    regenerate_chunks_on_biome_reload
+                        .run_if(resource_equals(TerrainGenMode::Local)),
+                    process_terrain_gen,
+                )
+                    .chain()
+                    .in_set(TerrainGenSet),
+            );
     }
 }
 
 #[derive(Component)]
-pub struct TerrainGenTask(Task<VoxelBuffer<Voxel, ChunkShape>>);
+pub struct TerrainGenTask(Task<PaletteBuffer<Voxel, ChunkShape>>);
@@ -1,13 +1,25 @@
+#![allow(clippy::type_complexity, clippy::module_inception)]
+
 use std::time::Duration;
 
 use bevy::prelude::*;
 use bevy_rapier3d::prelude::*;
 use bevy_renet::renet::{transport::NETCODE_KEY_BYTES, ChannelConfig, ConnectionConfig, SendType};
 use serde::{Deserialize, Serialize};
+use voxel::Voxel;
+
+/// Voxel data storage and terrain generation shared between the client and the server.
+pub mod voxel;
+
+/// The day and night cycle shared between the client and the server.
+pub mod time_of_day;
 
 pub const PRIVATE_KEY: &[u8; NETCODE_KEY_BYTES] = b"an example very very secret key."; // 32-bytes
 pub const PROTOCOL_ID: u64 = 7;
 
+/// Length of the side of a cubic chunk of voxels.
+pub const CHUNK_LENGTH: u32 = 32;
+
 #[derive(Debug, Component)]
 pub struct Player {
     pub id: u64,
@@ -41,6 +53,58 @@ pub struct MobSend {
     pub translation: Vec3,
 }
 
+/// A single voxel modification, `voxel` being the material id of the new voxel.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct VoxelEdit {
+    pub position: IVec3,
+    pub voxel: Voxel,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct VoxelEditBatch {
+    pub edits: Vec<VoxelEdit>,
+}
+
+/// A voxel which fell down its column by gravity, from the voxel it collapsed from to the voxel it landed in.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct VoxelFall {
+    pub origin: IVec3,
+    pub landing: IVec3,
+    pub voxel: Voxel,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct VoxelFallBatch {
+    pub falls: Vec<VoxelFall>,
+}
+
+/// Sent by clients to receive the edits made to the chunks they just loaded.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ChunkEditsRequest {
+    pub chunks: Vec<IVec3>,
+}
+
+/// Sent by clients streaming their terrain from the server to subscribe with their view radius, in chunks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkViewRadius {
+    pub horizontal: i32,
+    pub vertical: i32,
+}
+
+/// Sent by clients streaming their terrain from the server when they drop streamed chunks,
+/// so the server streams them again once they come back in view.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ChunksUnloaded {
+    pub chunks: Vec<IVec3>,
+}
+
+/// A chunk streamed by the server, `data` holding the voxels encoded with [`voxel::storage::encode_chunk`].
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChunkPayload {
+    pub key: IVec3,
+    pub data: Vec<u8>,
+}
+
 pub enum ClientChannel {
     Input,
     Command,
@@ -48,6 +112,12 @@ pub enum ClientChannel {
     Mobs,
     Chat,
     MobAttacked,
+    VoxelEdits,
+    ChunkEdits,
+    ChunkView,
+    ChunkUnload,
+    /// The voxels which fell by gravity, which may land out of the reach of the player.
+    VoxelFalls,
 }
 
 pub enum ServerChannel {
@@ -57,6 +127,8 @@ pub enum ServerChannel {
     NonNetworkedEntities,
     Host,
     MobAttacked,
+    VoxelEdits,
+    ChunkData,
 }
 
 #[derive(Debug, Serialize, Deserialize, Component)]
@@ -69,6 +141,18 @@ pub enum ServerMessages {
     PlayerRemove {
         id: u64,
     },
+    /// Sent on connect, before the world seed, so the clients flood the terrain up to the same height as the server.
+    SeaLevel {
+        sea_level: voxel::terraingen::water::SeaLevel,
+    },
+    /// Sent on connect so the clients generate the same terrain as the server.
+    WorldSeed {
+        seed: voxel::terraingen::WorldSeed,
+    },
+    /// Sent on connect and then regularly, so the day goes by at the same pace on the clients and the server.
+    TimeOfDay {
+        time_of_day: time_of_day::TimeOfDay,
+    },
 }
 
 #[derive(Debug, Serialize, Deserialize, Component)]
@@ -110,6 +194,11 @@ impl From<ClientChannel> for u8 {
             ClientChannel::Mobs => 3,
             ClientChannel::Chat => 4,
             ClientChannel::MobAttacked => 5,
+            ClientChannel::VoxelEdits => 6,
+            ClientChannel::ChunkEdits => 7,
+            ClientChannel::ChunkView => 8,
+            ClientChannel::ChunkUnload => 9,
+            ClientChannel::VoxelFalls => 10,
         }
     }
 }
@@ -159,6 +248,41 @@ impl ClientChannel {
                     resend_time: Duration::ZERO,
                 },
             },
+            ChannelConfig {
+                channel_id: Self::VoxelEdits.into(),
+                max_memory_usage_bytes: 5 * 1024 * 1024,
+                send_type: SendType::ReliableOrdered {
+                    resend_time: Duration::ZERO,
+                },
+            },
+            ChannelConfig {
+                channel_id: Self::ChunkEdits.into(),
+                max_memory_usage_bytes: 5 * 1024 * 1024,
+                send_type: SendType::ReliableOrdered {
+                    resend_time: Duration::ZERO,
+                },
+            },
+            ChannelConfig {
+                channel_id: Self::ChunkView.into(),
+                max_memory_usage_bytes: 5 * 1024 * 1024,
+                send_type: SendType::ReliableOrdered {
+                    resend_time: Duration::ZERO,
+                },
+            },
+            ChannelConfig {
+                channel_id: Self::ChunkUnload.into(),
+                max_memory_usage_bytes: 5 * 1024 * 1024,
+                send_type: SendType::ReliableOrdered {
+                    resend_time: Duration::ZERO,
+                },
+            },
+            ChannelConfig {
+                channel_id: Self::VoxelFalls.into(),
+                max_memory_usage_bytes: 5 * 1024 * 1024,
+                send_type: SendType::ReliableOrdered {
+                    resend_time: Duration::ZERO,
+                },
+            },
         ]
     }
 }
@@ -172,6 +296,8 @@ impl From<ServerChannel> for u8 {
             ServerChannel::Host => 3,
             ServerChannel::ChatChannel => 4,
             ServerChannel::MobAttacked => 5,
+            ServerChannel::VoxelEdits => 6,
+            ServerChannel::ChunkData => 7,
         }
     }
 }
@@ -211,6 +337,20 @@ impl ServerChannel {
                 max_memory_usage_bytes: 10 * 1024 * 1024,
                 send_type: SendType::Unreliable,
             },
+            ChannelConfig {
+                channel_id: Self::VoxelEdits.into(),
+                max_memory_usage_bytes: 10 * 1024 * 1024,
+                send_type: SendType::ReliableOrdered {
+                    resend_time: Duration::from_millis(200),
+                },
+            },
+            ChannelConfig {
+                channel_id: Self::ChunkData.into(),
+                max_memory_usage_bytes: 64 * 1024 * 1024,
+                send_type: SendType::ReliableOrdered {
+                    resend_time: Duration::from_millis(200),
+                },
+            },
         ]
     }
 }
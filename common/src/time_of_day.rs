@@ -0,0 +1,58 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::{Resource, Vec3};
+use serde::{Deserialize, Serialize};
+
+/// Tilt of the path of the sun and the moon towards the south, so the light is never straight vertical.
+const CELESTIAL_TILT: f32 = 0.35;
+
+/// The time of the day, which the server advances and sends to its clients.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TimeOfDay {
+    /// The elapsed share of the day, `0.0` being midnight, `0.25` sunrise, `0.5` noon and `0.75` sunset.
+    pub time: f32,
+    /// Seconds a whole day lasts.
+    pub day_length: f32,
+}
+
+impl Default for TimeOfDay {
+    fn default() -> Self {
+        Self {
+            time: 0.3,
+            day_length: 20.0 * 60.0,
+        }
+    }
+}
+
+impl TimeOfDay {
+    /// Advances the time by the specified seconds, wrapping around to the next day.
+    pub fn advance(&mut self, seconds: f32) {
+        if self.day_length > 0.0 {
+            self.time = (self.time + seconds / self.day_length).rem_euclid(1.0);
+        }
+    }
+
+    /// Returns the direction from the ground towards the sun, rising in the east and setting in the west.
+    pub fn sun_direction(&self) -> Vec3 {
+        let angle = (self.time - 0.25) * TAU;
+        Vec3::new(angle.cos(), angle.sin(), CELESTIAL_TILT).normalize()
+    }
+
+    /// Returns the direction from the ground towards the moon, which stands opposite to the sun.
+    pub fn moon_direction(&self) -> Vec3 {
+        let sun = self.sun_direction();
+        Vec3::new(-sun.x, -sun.y, sun.z)
+    }
+
+    /// Returns how much of the daylight reaches the ground, from `0.0` at night to `1.0` once the sun is up.
+    pub fn daylight(&self) -> f32 {
+        let height = self.sun_direction().y;
+        let t = ((height + 0.1) / 0.35).clamp(0.0, 1.0);
+        t * t * (3.0 - 2.0 * t)
+    }
+
+    /// Whether the sun is below the horizon.
+    pub fn is_night(&self) -> bool {
+        self.sun_direction().y < 0.0
+    }
+}
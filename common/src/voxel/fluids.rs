@@ -0,0 +1,417 @@
+use std::collections::BTreeSet;
+
+use super::{
+    material::{material_ids_with_flags, VoxelMaterialFlags},
+    storage::ChunkMap,
+    ChunkShape, Voxel,
+};
+use crate::{VoxelEdit, CHUNK_LENGTH};
+use bevy::{math::IVec3, prelude::Resource};
+
+/// Seconds between two steps of the fluid simulation, shared by the client and the server so liquids flow at the same pace.
+pub const FLUID_TICK_INTERVAL: f32 = 0.25;
+
+/// State of the liquid voxels which never dry out.
+pub const FLUID_SOURCE_STATE: u8 = 0;
+
+/// Highest flow level, the liquid stops spreading sideways once it flowed that many voxels away from a source.
+pub const FLUID_MAX_FLOW: u8 = 7;
+
+/// State bit of the liquid voxels falling from the voxel above them, which spread as far as a source once they land.
+pub const FLUID_FALLING: u8 = 1 << 3;
+
+/// Maximum number of voxels updated per step, the remaining ones are updated during the next steps.
+const MAX_FLUID_UPDATES: usize = 4096;
+
+/// The directions liquids spread sideways in, in the order neighbours are looked up.
+const SIDEWAYS: [IVec3; 4] = [IVec3::NEG_X, IVec3::X, IVec3::NEG_Z, IVec3::Z];
+
+/// Returns the minimums of the chunks holding the voxels read while updating the voxel at the specified position,
+/// which are the chunks of the voxels around it.
+pub fn neighbourhood_chunks(pos: IVec3) -> [IVec3; 8] {
+    let mask = !IVec3::splat(CHUNK_LENGTH as i32 - 1);
+    let mut keys = [IVec3::ZERO; 8];
+    for (i, key) in keys.iter_mut().enumerate() {
+        let corner = IVec3::new(i as i32 & 1, (i as i32 >> 1) & 1, (i as i32 >> 2) & 1) * 2 - 1;
+        *key = mask & (pos + corner);
+    }
+    keys
+}
+
+/// A cellular simulation of the liquids flowing through the voxels of a [`ChunkMap`].
+///
+/// Liquid voxels store their flow level in their state: sources are at [`FLUID_SOURCE_STATE`],
+/// and the liquid flowing from them increases by one level per voxel up to [`FLUID_MAX_FLOW`].
+/// Each step only updates the voxels around the previous changes, see [`FluidSimulation::activate`],
+/// and every voxel is computed from the world as it was at the start of the step,
+/// so the same edits always lead to the same liquids.
+/// No material is simulated until the liquids are set, see [`FluidSimulation::set_liquids_from_definitions`].
+#[derive(Resource, Clone, Debug, Default)]
+pub struct FluidSimulation {
+    liquids: Vec<u16>,
+    /// Positions to update during the next steps, sorted so the updates don't depend on the order of the edits.
+    active: BTreeSet<[i32; 3]>,
+}
+
+impl FluidSimulation {
+    /// Replaces the ids of the materials simulated as liquids.
+    pub fn set_liquids(&mut self, liquids: impl IntoIterator<Item = u16>) {
+        self.liquids = liquids.into_iter().collect();
+        self.liquids.sort_unstable();
+    }
+
+    /// Simulates the materials flagged as liquids in the contents of a `.materials.ron` material definition file.
+    pub fn set_liquids_from_definitions(
+        &mut self,
+        bytes: &[u8],
+    ) -> Result<(), ron::error::SpannedError> {
+        self.set_liquids(material_ids_with_flags(bytes, VoxelMaterialFlags::LIQUID)?);
+        Ok(())
+    }
+
+    #[inline]
+    pub fn is_liquid(&self, voxel: Voxel) -> bool {
+        !voxel.is_empty() && self.liquids.contains(&voxel.id)
+    }
+
+    /// Schedules the update of the voxels around a position, which must be called when the voxel there is edited.
+    pub fn activate(&mut self, pos: IVec3) {
+        for z in -1..=1 {
+            for y in -1..=1 {
+                for x in -1..=1 {
+                    self.active.insert((pos + IVec3::new(x, y, z)).to_array());
+                }
+            }
+        }
+    }
+
+    /// Returns the number of voxels waiting to be updated.
+    pub fn num_active(&self) -> usize {
+        self.active.len()
+    }
+
+    /// Whether the voxel at the specified position is waiting to be updated.
+    pub fn is_active(&self, pos: IVec3) -> bool {
+        self.active.contains(&pos.to_array())
+    }
+
+    /// Returns the positions of the voxels waiting to be updated.
+    pub fn iter_active(&self) -> impl Iterator<Item = IVec3> + '_ {
+        self.active.iter().copied().map(IVec3::from)
+    }
+
+    /// Advances the simulation by one step, returning the voxels which changed.
+    /// Voxels outside of the loaded chunks are left untouched and stop the liquids like solid voxels,
+    /// except the voxels next to the chunks still loading, which wait for these chunks to update.
+    pub fn tick(
+        &mut self,
+        chunks: &mut ChunkMap<Voxel, ChunkShape>,
+        is_loading: impl Fn(IVec3) -> bool,
+    ) -> Vec<VoxelEdit> {
+        let mut edits = Vec::new();
+        let mut waiting = Vec::new();
+
+        for _ in 0..MAX_FLUID_UPDATES {
+            let Some(pos) = self.active.pop_first().map(IVec3::from) else {
+                break;
+            };
+
+            if neighbourhood_chunks(pos).into_iter().any(&is_loading) {
+                waiting.push(pos.to_array());
+                continue;
+            }
+
+            if let Some(voxel) = self.next_voxel_at(chunks, pos) {
+                edits.push(VoxelEdit {
+                    position: pos,
+                    voxel,
+                });
+            }
+        }
+
+        for edit in edits.iter() {
+            if let Some(mut voxel) = chunks.voxel_at_mut(edit.position) {
+                *voxel = edit.voxel;
+            }
+            self.activate(edit.position);
+        }
+        self.active.extend(waiting);
+
+        edits
+    }
+
+    /// Computes the voxel at the specified position after the current step, if it changes.
+    fn next_voxel_at(&self, chunks: &ChunkMap<Voxel, ChunkShape>, pos: IVec3) -> Option<Voxel> {
+        let current = chunks.voxel_at(pos)?;
+
+        // only empty voxels and liquids flowing from a source are ever replaced.
+        if !(current.is_empty() || self.is_liquid(current) && current.state != FLUID_SOURCE_STATE) {
+            return None;
+        }
+
+        let next = self
+            .inflow_at(chunks, pos)
+            .unwrap_or(if current.is_empty() {
+                current
+            } else {
+                // the liquid dries out once nothing flows into it anymore.
+                Voxel::EMPTY_VOXEL
+            });
+
+        (next != current).then_some(next)
+    }
+
+    /// Returns the liquid flowing into the specified position from the voxels above or beside it.
+    fn inflow_at(&self, chunks: &ChunkMap<Voxel, ChunkShape>, pos: IVec3) -> Option<Voxel> {
+        if let Some(above) = chunks
+            .voxel_at(pos + IVec3::Y)
+            .filter(|voxel| self.is_liquid(*voxel))
+        {
+            return Some(Voxel::new(above.id).with_state(FLUID_FALLING));
+        }
+
+        let mut inflow: Option<Voxel> = None;
+
+        for direction in SIDEWAYS {
+            let Some(neighbour) = chunks
+                .voxel_at(pos + direction)
+                .filter(|voxel| self.is_liquid(*voxel))
+            else {
+                continue;
+            };
+
+            // flowing liquids fall down rather than spread sideways whenever they can.
+            if neighbour.state != FLUID_SOURCE_STATE
+                && self.can_fall_into(chunks.voxel_at(pos + direction - IVec3::Y))
+            {
+                continue;
+            }
+
+            let level = if neighbour.state & FLUID_FALLING != 0 {
+                FLUID_SOURCE_STATE
+            } else {
+                neighbour.state
+            };
+
+            if level < FLUID_MAX_FLOW && inflow.is_none_or(|inflow| level + 1 < inflow.state) {
+                inflow = Some(Voxel::new(neighbour.id).with_state(level + 1));
+            }
+        }
+
+        inflow
+    }
+
+    /// Whether a liquid can fall into the specified voxel, which is the case of empty voxels and flowing liquids.
+    fn can_fall_into(&self, voxel: Option<Voxel>) -> bool {
+        voxel.is_some_and(|voxel| {
+            voxel.is_empty() || self.is_liquid(voxel) && voxel.state != FLUID_SOURCE_STATE
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxel::{
+        material::VoxelMaterial,
+        materials::{Rock, Water},
+        storage::VoxelBuffer,
+    };
+
+    /// A single chunk with a rock floor at the bottom, the voxels around it acting as walls.
+    fn floor_chunk() -> ChunkMap<Voxel, ChunkShape> {
+        let mut buffer = VoxelBuffer::<Voxel, ChunkShape>::new_empty(ChunkShape {});
+        for z in 0..CHUNK_LENGTH {
+            for x in 0..CHUNK_LENGTH {
+                *buffer.voxel_at_mut([x, 0, z].into()) = Voxel::new(Rock::ID);
+            }
+        }
+
+        let mut chunks = ChunkMap::new(ChunkShape {});
+        chunks.insert(IVec3::ZERO, buffer);
+        chunks
+    }
+
+    fn water_simulation() -> FluidSimulation {
+        let mut sim = FluidSimulation::default();
+        sim.set_liquids([Water::ID]);
+        sim
+    }
+
+    fn edit(
+        sim: &mut FluidSimulation,
+        chunks: &mut ChunkMap<Voxel, ChunkShape>,
+        pos: IVec3,
+        voxel: Voxel,
+    ) {
+        *chunks.voxel_at_mut(pos).unwrap() = voxel;
+        sim.activate(pos);
+    }
+
+    /// Steps the simulation until no voxel is waiting to be updated anymore.
+    fn settle(sim: &mut FluidSimulation, chunks: &mut ChunkMap<Voxel, ChunkShape>) {
+        for _ in 0..256 {
+            if sim.num_active() == 0 {
+                return;
+            }
+            sim.tick(chunks, |_| false);
+        }
+        panic!("the liquids never settled");
+    }
+
+    fn voxel(chunks: &ChunkMap<Voxel, ChunkShape>, x: i32, y: i32, z: i32) -> Voxel {
+        chunks.voxel_at(IVec3::new(x, y, z)).unwrap()
+    }
+
+    #[test]
+    fn liquids_spread_sideways_up_to_the_max_flow() {
+        let (mut sim, mut chunks) = (water_simulation(), floor_chunk());
+        edit(
+            &mut sim,
+            &mut chunks,
+            IVec3::new(16, 1, 16),
+            Voxel::new(Water::ID),
+        );
+        settle(&mut sim, &mut chunks);
+
+        for distance in 1..=FLUID_MAX_FLOW as i32 {
+            let expected = Voxel::new(Water::ID).with_state(distance as u8);
+            assert_eq!(voxel(&chunks, 16 + distance, 1, 16), expected);
+            assert_eq!(voxel(&chunks, 16, 1, 16 - distance), expected);
+        }
+        assert!(voxel(&chunks, 16 + FLUID_MAX_FLOW as i32 + 1, 1, 16).is_empty());
+        assert!(voxel(&chunks, 16, 2, 16 + 1).is_empty());
+    }
+
+    #[test]
+    fn liquids_fall_then_spread_once_landed() {
+        let (mut sim, mut chunks) = (water_simulation(), floor_chunk());
+        // a ledge holding the source, which only leaves the liquid the way down.
+        for z in 15..=17 {
+            for x in 15..=17 {
+                edit(
+                    &mut sim,
+                    &mut chunks,
+                    IVec3::new(x, 10, z),
+                    Voxel::new(Rock::ID),
+                );
+            }
+        }
+        edit(
+            &mut sim,
+            &mut chunks,
+            IVec3::new(16, 10, 16),
+            Voxel::new(Water::ID),
+        );
+        settle(&mut sim, &mut chunks);
+
+        for y in 1..10 {
+            assert_eq!(
+                voxel(&chunks, 16, y, 16),
+                Voxel::new(Water::ID).with_state(FLUID_FALLING)
+            );
+        }
+        // falling liquids only spread sideways on the floor.
+        assert!(voxel(&chunks, 17, 9, 16).is_empty());
+        assert!(voxel(&chunks, 17, 2, 16).is_empty());
+        for distance in 1..=FLUID_MAX_FLOW as i32 {
+            assert_eq!(
+                voxel(&chunks, 16 - distance, 1, 16),
+                Voxel::new(Water::ID).with_state(distance as u8)
+            );
+        }
+    }
+
+    #[test]
+    fn liquids_dry_out_once_the_source_is_removed() {
+        let (mut sim, mut chunks) = (water_simulation(), floor_chunk());
+        edit(
+            &mut sim,
+            &mut chunks,
+            IVec3::new(16, 1, 16),
+            Voxel::new(Water::ID),
+        );
+        settle(&mut sim, &mut chunks);
+
+        edit(
+            &mut sim,
+            &mut chunks,
+            IVec3::new(16, 1, 16),
+            Voxel::EMPTY_VOXEL,
+        );
+        settle(&mut sim, &mut chunks);
+
+        let buffer = chunks.buffer_at(IVec3::ZERO).unwrap().to_buffer();
+        assert!(buffer.slice().iter().all(|voxel| voxel.id != Water::ID));
+    }
+
+    #[test]
+    fn edits_in_any_order_lead_to_the_same_liquids() {
+        let edits = [
+            (IVec3::new(10, 1, 16), Voxel::new(Water::ID)),
+            (IVec3::new(20, 1, 16), Voxel::new(Water::ID)),
+            (IVec3::new(15, 1, 16), Voxel::new(Rock::ID)),
+            (IVec3::new(15, 1, 12), Voxel::new(Water::ID)),
+        ];
+
+        let (mut sim, mut chunks) = (water_simulation(), floor_chunk());
+        for (pos, voxel) in edits {
+            edit(&mut sim, &mut chunks, pos, voxel);
+        }
+        let (mut reversed_sim, mut reversed_chunks) = (water_simulation(), floor_chunk());
+        for (pos, voxel) in edits.into_iter().rev() {
+            edit(&mut reversed_sim, &mut reversed_chunks, pos, voxel);
+        }
+
+        while sim.num_active() > 0 || reversed_sim.num_active() > 0 {
+            let changes = |edits: Vec<VoxelEdit>| -> Vec<(IVec3, Voxel)> {
+                edits
+                    .iter()
+                    .map(|edit| (edit.position, edit.voxel))
+                    .collect()
+            };
+            assert_eq!(
+                changes(sim.tick(&mut chunks, |_| false)),
+                changes(reversed_sim.tick(&mut reversed_chunks, |_| false))
+            );
+        }
+
+        assert_eq!(
+            chunks.buffer_at(IVec3::ZERO).unwrap().to_buffer().slice(),
+            reversed_chunks
+                .buffer_at(IVec3::ZERO)
+                .unwrap()
+                .to_buffer()
+                .slice()
+        );
+    }
+
+    #[test]
+    fn liquids_wait_for_the_chunks_loading_around_them() {
+        let (mut sim, mut chunks) = (water_simulation(), floor_chunk());
+        let loading = IVec3::new(CHUNK_LENGTH as i32, 0, 0);
+        edit(
+            &mut sim,
+            &mut chunks,
+            IVec3::new(30, 1, 16),
+            Voxel::new(Water::ID),
+        );
+
+        for _ in 0..16 {
+            sim.tick(&mut chunks, |key| key == loading);
+        }
+        // the voxels next to the loading chunk stay waiting, the other ones flow.
+        assert!(sim.is_active(IVec3::new(31, 1, 16)));
+        assert!(voxel(&chunks, 31, 1, 16).is_empty());
+        assert_eq!(
+            voxel(&chunks, 29, 1, 16),
+            Voxel::new(Water::ID).with_state(1)
+        );
+
+        sim.tick(&mut chunks, |_| false);
+        assert_eq!(
+            voxel(&chunks, 31, 1, 16),
+            Voxel::new(Water::ID).with_state(1)
+        );
+    }
+}
@@ -0,0 +1,78 @@
+use bitflags::bitflags;
+use serde::{Deserialize, Serialize};
+
+use super::{materials::voxel_by_name, Voxel};
+
+/// The asset file holding the material definitions shipped with the game, the only place the materials are defined.
+/// The server reads the flags of the materials from it too, see [`material_ids_with_flags`].
+pub const MATERIAL_DEFINITIONS_PATH: &str = "voxel.materials.ron";
+
+/// Helper / marker trait for voxel materials.
+pub trait VoxelMaterial {
+    const ID: u16;
+
+    fn into_voxel() -> Voxel {
+        Voxel::new(Self::ID)
+    }
+}
+
+#[macro_export]
+macro_rules! voxel_material {
+    ($types: ident, $id: expr) => {
+        pub struct $types;
+        impl $types {
+            pub const NAME: &'static str = stringify!($types);
+        }
+        impl $crate::voxel::material::VoxelMaterial for $types {
+            const ID: u16 = $id;
+        }
+    };
+}
+
+bitflags! {
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(transparent)]
+    pub struct VoxelMaterialFlags : u32 {
+        const SOLID = 0;
+        const LIQUID = 1 << 1;
+        const UNBREAKABLE = 1 << 2;
+        /// The material is drawn in the translucent pass and doesn't hide the faces behind it.
+        const TRANSLUCENT = 1 << 3;
+        /// The voxels of the material fall down when nothing holds them up.
+        const GRAVITY = 1 << 4;
+    }
+}
+
+impl Default for VoxelMaterialFlags {
+    fn default() -> Self {
+        Self::SOLID
+    }
+}
+
+/// A material of a `.materials.ron` material definition file, reduced to what tells the materials apart.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct MaterialFlagsDefinition {
+    name: String,
+    id: Option<u16>,
+    flags: VoxelMaterialFlags,
+}
+
+/// Returns the ids of the materials with all the specified flags in the contents of a `.materials.ron` material definition file,
+/// the materials known by the game being looked up by name like the renderer does.
+pub fn material_ids_with_flags(
+    bytes: &[u8],
+    flags: VoxelMaterialFlags,
+) -> Result<Vec<u16>, ron::error::SpannedError> {
+    let definitions = ron::de::from_bytes::<Vec<MaterialFlagsDefinition>>(bytes)?;
+
+    Ok(definitions
+        .into_iter()
+        .filter(|definition| definition.flags.contains(flags))
+        .filter_map(|definition| {
+            voxel_by_name(&definition.name)
+                .map(|voxel| voxel.id)
+                .or(definition.id)
+        })
+        .collect())
+}
@@ -0,0 +1,50 @@
+use crate::voxel_material;
+
+use super::{material::VoxelMaterial, Voxel};
+
+voxel_material!(Dirt, 1);
+voxel_material!(Sand, 2);
+voxel_material!(Grass, 3);
+voxel_material!(Rock, 4);
+voxel_material!(Snow, 5);
+voxel_material!(Water, 6);
+voxel_material!(Sandstone, 7);
+voxel_material!(Bedrock, 8);
+voxel_material!(Cactus, 9);
+voxel_material!(Wood, 10);
+voxel_material!(Leaves, 11);
+voxel_material!(PineLeaves, 12);
+voxel_material!(PineWood, 13);
+voxel_material!(CoalOre, 14);
+voxel_material!(IronOre, 15);
+voxel_material!(GoldOre, 16);
+voxel_material!(DiamondOre, 17);
+
+/// The name and id of every material, for looking them up from data files.
+const MATERIALS: [(&str, u16); 17] = [
+    (Dirt::NAME, Dirt::ID),
+    (Sand::NAME, Sand::ID),
+    (Grass::NAME, Grass::ID),
+    (Rock::NAME, Rock::ID),
+    (Snow::NAME, Snow::ID),
+    (Water::NAME, Water::ID),
+    (Sandstone::NAME, Sandstone::ID),
+    (Bedrock::NAME, Bedrock::ID),
+    (Cactus::NAME, Cactus::ID),
+    (Wood::NAME, Wood::ID),
+    (Leaves::NAME, Leaves::ID),
+    (PineLeaves::NAME, PineLeaves::ID),
+    (PineWood::NAME, PineWood::ID),
+    (CoalOre::NAME, CoalOre::ID),
+    (IronOre::NAME, IronOre::ID),
+    (GoldOre::NAME, GoldOre::ID),
+    (DiamondOre::NAME, DiamondOre::ID),
+];
+
+/// Returns the voxel of the material with the specified name, if any.
+pub fn voxel_by_name(name: &str) -> Option<Voxel> {
+    MATERIALS
+        .iter()
+        .find(|(material, _)| *material == name)
+        .map(|(_, id)| Voxel::new(*id))
+}
@@ -0,0 +1,42 @@
+use bevy::math::IVec3;
+use ndshape::ConstShape3u32;
+
+pub use crate::CHUNK_LENGTH;
+
+/// Storage primitives for storing voxel data
+pub mod storage;
+
+/// Terrain generator.
+pub mod terraingen;
+
+/// Voxel material identifiers shared by the terrain generator and the renderer.
+pub mod material;
+
+/// The base materials of the voxel world.
+pub mod materials;
+
+/// Cellular simulation of the liquids flowing through the voxel world.
+pub mod fluids;
+
+/// rust ports of signed distance field functions for use in world generation.
+pub mod sdf;
+
+mod voxel;
+pub use voxel::*;
+
+pub const CHUNK_LENGTH_U: usize = CHUNK_LENGTH as usize;
+pub type ChunkShape = ConstShape3u32<CHUNK_LENGTH, CHUNK_LENGTH, CHUNK_LENGTH>;
+
+/// Returns the keys of the chunks within a cylindrical view radius (in chunks) around a chunk, skipping the ones below the world bottom.
+pub fn chunks_in_view(
+    center: IVec3,
+    horizontal: i32,
+    vertical: i32,
+) -> impl Iterator<Item = IVec3> {
+    (-horizontal..horizontal)
+        .flat_map(move |x| (-horizontal..horizontal).map(move |z| (x, z)))
+        .filter(move |(x, z)| x.pow(2) + z.pow(2) < horizontal.pow(2))
+        .flat_map(move |(x, z)| (-vertical..vertical).map(move |y| IVec3::new(x, y, z)))
+        .map(move |offset| center + offset * CHUNK_LENGTH as i32)
+        .filter(|key| key.y >= 0)
+}
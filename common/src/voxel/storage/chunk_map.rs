@@ -0,0 +1,203 @@
+use ilattice::{morton::Morton3i32, vector::Map as VecMap};
+use std::{collections::BTreeMap, hash::Hash};
+
+use bevy::{
+    math::{IVec3, Vec3},
+    prelude::Resource,
+};
+use ndshape::Shape;
+
+use crate::voxel::CHUNK_LENGTH;
+
+use super::palette::{PaletteBuffer, PaletteVoxelMut};
+
+/// Provides an interface to query or modify voxel data for worlds or scenes split into multiple voxel data buffers of a same shape with no level of detail.
+/// Buffers are stored palette-compressed, see [`PaletteBuffer`].
+#[derive(Resource)]
+pub struct ChunkMap<V, S>
+where
+    V: Clone + Copy + Default + PartialEq + Eq + Hash,
+    S: Shape<3, Coord = u32> + Clone,
+{
+    chunks: BTreeMap<Morton3i32, PaletteBuffer<V, S>>,
+    shape_mask: IVec3,
+    shape: S,
+}
+
+#[allow(dead_code)]
+impl<V, S> ChunkMap<V, S>
+where
+    V: Clone + Copy + Default + PartialEq + Eq + Hash,
+    S: Shape<3, Coord = u32> + Clone,
+{
+    pub fn new(chunk_shape: S) -> Self {
+        Self {
+            chunks: Default::default(),
+            shape_mask: !(IVec3::from(chunk_shape.as_array().map(|x| x as i32)) - IVec3::ONE),
+            shape: chunk_shape,
+        }
+    }
+
+    pub fn voxel_at(&self, pos: IVec3) -> Option<V> {
+        let chunk_minimum = pos & self.shape_mask;
+        let local_minimum = ilattice::glam::IVec3::from(pos.to_array())
+            .map(|x| x.rem_euclid(CHUNK_LENGTH as i32))
+            .as_uvec3();
+
+        self.buffer_at(chunk_minimum)
+            .map(|buffer| buffer.voxel_at(local_minimum))
+    }
+
+    pub fn voxel_at_mut(&mut self, pos: IVec3) -> Option<PaletteVoxelMut<'_, V, S>> {
+        let chunk_minimum = pos & self.shape_mask;
+        let local_minimum = ilattice::glam::IVec3::from(pos.to_array())
+            .map(|x| x.rem_euclid(CHUNK_LENGTH as i32))
+            .as_uvec3();
+
+        self.buffer_at_mut(chunk_minimum)
+            .map(|buffer| buffer.voxel_at_mut(local_minimum))
+    }
+
+    /// Checks whether there's a buffer at the specified minimum.
+    #[inline]
+    pub fn exists(&self, minimum: IVec3) -> bool {
+        let minimum = ilattice::glam::IVec3::from(minimum.to_array());
+        self.chunks.contains_key(&minimum.into())
+    }
+
+    /// Returns a reference to the [`PaletteBuffer<V, S>`] at the specified minimum if there's one.
+    #[inline]
+    pub fn buffer_at(&self, minimum: IVec3) -> Option<&PaletteBuffer<V, S>> {
+        let minimum = ilattice::glam::IVec3::from(minimum.to_array());
+        self.chunks.get(&minimum.into())
+    }
+
+    /// Returns a mutable reference to the [`PaletteBuffer<V, S>`] at the specified minimum if there's one.
+    #[inline]
+    pub fn buffer_at_mut(&mut self, minimum: IVec3) -> Option<&mut PaletteBuffer<V, S>> {
+        let minimum = ilattice::glam::IVec3::from(minimum.to_array());
+        self.chunks.get_mut(&minimum.into())
+    }
+
+    /// Inserts a new buffer at the specified minimum, compressing it if needed.
+    pub fn insert(&mut self, minimum: IVec3, buffer: impl Into<PaletteBuffer<V, S>>) {
+        let minimum = ilattice::glam::IVec3::from(minimum.to_array());
+        let buffer = buffer.into();
+
+        assert!(buffer.shape().as_array() == self.shape.as_array());
+        self.chunks.insert(minimum.into(), buffer);
+    }
+
+    /// Inserts a new buffer inititalized with the default value of [`V`] at the specified minimum.
+    pub fn insert_empty(&mut self, minimum: IVec3) {
+        let minimum = ilattice::glam::IVec3::from(minimum.to_array());
+        self.chunks.insert(
+            minimum.into(),
+            PaletteBuffer::<V, S>::new_empty(self.shape.clone()),
+        );
+    }
+
+    /// Inserts buffers from an iterator passed as a parameter
+    pub fn insert_batch<T: IntoIterator<Item = (Morton3i32, PaletteBuffer<V, S>)>>(
+        &mut self,
+        iter: T,
+    ) {
+        self.chunks.extend(iter);
+    }
+
+    /// Removes the buffer at the specified minimum and returns it if it exists.
+    pub fn remove(&mut self, pos: IVec3) -> Option<PaletteBuffer<V, S>> {
+        let pos = ilattice::glam::IVec3::from(pos.to_array());
+        self.chunks.remove(&pos.into())
+    }
+
+    /// Returns an iterator over the minimums of the stored buffers.
+    pub fn iter_keys(&self) -> impl Iterator<Item = IVec3> + '_ {
+        self.chunks
+            .keys()
+            .map(|key| IVec3::from(<[i32; 3]>::from(*key)))
+    }
+
+    /// Returns the number of bytes used by the voxel data of all the loaded buffers.
+    pub fn heap_size(&self) -> usize {
+        self.chunks.values().map(PaletteBuffer::heap_size).sum()
+    }
+
+    /// Walks the voxel grid along a ray using a DDA traversal and returns the first voxel matching the predicate.
+    /// Voxels in unloaded chunks are skipped.
+    pub fn raycast(
+        &self,
+        origin: Vec3,
+        direction: Vec3,
+        max_distance: f32,
+        predicate: impl Fn(V) -> bool,
+    ) -> Option<VoxelRayHit> {
+        let direction = direction.normalize_or_zero();
+        if direction == Vec3::ZERO {
+            return None;
+        }
+
+        let mut voxel = origin.floor().as_ivec3();
+        // the ray never steps nor crosses a boundary along the axes it is parallel to.
+        let moving = direction.cmpne(Vec3::ZERO);
+        let step = IVec3::select(moving, direction.signum().as_ivec3(), IVec3::ZERO);
+
+        // distance along the ray needed to cross one voxel on each axis.
+        let delta = direction.recip().abs();
+        let mut next_boundary = Vec3::select(
+            moving,
+            Vec3::select(
+                direction.cmpgt(Vec3::ZERO),
+                (voxel.as_vec3() + Vec3::ONE - origin) * delta,
+                (origin - voxel.as_vec3()) * delta,
+            ),
+            Vec3::splat(f32::INFINITY),
+        );
+
+        let mut normal = IVec3::ZERO;
+        let mut distance = 0.0;
+
+        while distance <= max_distance {
+            if self.voxel_at(voxel).is_some_and(&predicate) {
+                return Some(VoxelRayHit {
+                    position: voxel,
+                    normal,
+                });
+            }
+
+            let axis = if next_boundary.x < next_boundary.y {
+                if next_boundary.x < next_boundary.z {
+                    0
+                } else {
+                    2
+                }
+            } else if next_boundary.y < next_boundary.z {
+                1
+            } else {
+                2
+            };
+
+            distance = next_boundary[axis];
+            next_boundary[axis] += delta[axis];
+            voxel[axis] += step[axis];
+            normal = IVec3::ZERO;
+            normal[axis] = -step[axis];
+        }
+
+        None
+    }
+
+    #[inline]
+    pub const fn shape_mask(&self) -> IVec3 {
+        self.shape_mask
+    }
+}
+
+/// The result of a [`ChunkMap::raycast`].
+#[derive(Clone, Copy, Debug)]
+pub struct VoxelRayHit {
+    /// World position of the voxel hit by the ray.
+    pub position: IVec3,
+    /// Normal of the voxel face the ray entered through, zero if the ray started inside the voxel.
+    pub normal: IVec3,
+}
@@ -1,5 +1,11 @@
 mod buffer;
 pub use buffer::*;
 
+mod palette;
+pub use palette::*;
+
 mod chunk_map;
 pub use chunk_map::*;
+
+mod region;
+pub use region::*;
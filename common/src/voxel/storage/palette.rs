@@ -0,0 +1,429 @@
+use std::ops::{Deref, DerefMut};
+
+use ilattice::extent::Extent;
+use ilattice::glam::UVec3;
+use ndshape::Shape;
+
+use super::VoxelBuffer;
+
+/// Storage backing a [`PaletteBuffer`].
+#[derive(Clone)]
+enum PaletteStorage<V> {
+    /// Every voxel of the buffer holds the same value.
+    Uniform(V),
+    /// Voxels are stored as bit-packed indices into a palette of distinct values.
+    Packed {
+        palette: Vec<V>,
+        bits: u32,
+        words: Box<[u64]>,
+    },
+}
+
+/// A buffer of typed voxel data compressed using a palette of the distinct values it holds.
+///
+/// Chunks filled with a single value (e.g. air or rock) only store that value, other chunks store
+/// one index per voxel using as few bits as the palette size requires.
+#[derive(Clone)]
+pub struct PaletteBuffer<V, S: Shape<3, Coord = u32>>
+where
+    V: Copy + Clone + Default + PartialEq,
+{
+    storage: PaletteStorage<V>,
+    shape: S,
+}
+
+impl<V, S: Shape<3, Coord = u32>> PaletteBuffer<V, S>
+where
+    V: Copy + Clone + Default + PartialEq,
+{
+    #[inline]
+    pub fn new(shape: S, initial_val: V) -> Self {
+        Self {
+            storage: PaletteStorage::Uniform(initial_val),
+            shape,
+        }
+    }
+
+    #[inline]
+    pub fn new_empty(shape: S) -> Self {
+        Self::new(shape, Default::default())
+    }
+
+    /// Compresses the data of a [`VoxelBuffer<V, S>`].
+    pub fn from_buffer(buffer: &VoxelBuffer<V, S>) -> Self
+    where
+        S: Clone,
+    {
+        let mut palette = Vec::new();
+        let indices: Vec<usize> = buffer
+            .slice()
+            .iter()
+            .map(|voxel| palette_index(&mut palette, *voxel))
+            .collect();
+
+        let mut compressed = Self::new(buffer.shape().clone(), palette[0]);
+        if palette.len() > 1 {
+            let bits = bits_for_palette_len(palette.len());
+            let mut words = packed_words(buffer.shape().size(), bits);
+            indices
+                .into_iter()
+                .enumerate()
+                .for_each(|(i, index)| write_index(&mut words, bits, i, index));
+
+            compressed.storage = PaletteStorage::Packed {
+                palette,
+                bits,
+                words,
+            };
+        }
+
+        compressed
+    }
+
+    /// Decompresses this buffer into a [`VoxelBuffer<V, S>`].
+    pub fn to_buffer(&self) -> VoxelBuffer<V, S>
+    where
+        S: Clone,
+    {
+        match &self.storage {
+            PaletteStorage::Uniform(val) => VoxelBuffer::new(self.shape.clone(), *val),
+            PaletteStorage::Packed {
+                palette,
+                bits,
+                words,
+            } => {
+                let mut buffer = VoxelBuffer::new_empty(self.shape.clone());
+                buffer
+                    .slice_mut()
+                    .iter_mut()
+                    .enumerate()
+                    .for_each(|(i, voxel)| *voxel = palette[read_index(words, *bits, i)]);
+                buffer
+            }
+        }
+    }
+
+    // Returns the voxel at the querried position in local space.
+    #[inline]
+    pub fn voxel_at(&self, pos: UVec3) -> V {
+        match &self.storage {
+            PaletteStorage::Uniform(val) => *val,
+            PaletteStorage::Packed {
+                palette,
+                bits,
+                words,
+            } => palette[read_index(words, *bits, self.shape.linearize(pos.to_array()) as usize)],
+        }
+    }
+
+    // Returns a handle to the the voxel at the querried position in local space, the value is written back when the handle is dropped.
+    #[inline]
+    pub fn voxel_at_mut(&mut self, pos: UVec3) -> PaletteVoxelMut<'_, V, S> {
+        let value = self.voxel_at(pos);
+        PaletteVoxelMut {
+            buffer: self,
+            pos,
+            original: value,
+            value,
+        }
+    }
+
+    /// Sets the voxel at the querried position in local space.
+    pub fn set_voxel(&mut self, pos: UVec3, val: V) {
+        let linear = self.shape.linearize(pos.to_array()) as usize;
+        self.set_linear(linear, val);
+    }
+
+    /// Fills an extent of this buffer with the specified value.
+    pub fn fill_extent(&mut self, extent: Extent<UVec3>, val: V) {
+        let shape = UVec3::from(self.shape.as_array());
+
+        if extent.minimum == UVec3::ZERO && extent.shape == shape {
+            self.storage = PaletteStorage::Uniform(val);
+            return;
+        }
+
+        if matches!(self.storage, PaletteStorage::Uniform(current) if current == val) {
+            return;
+        }
+
+        extent.iter3().for_each(|pos| self.set_voxel(pos, val));
+    }
+
+    /// Returns whether every voxel of this buffer holds the same value.
+    #[inline]
+    pub fn is_uniform(&self) -> bool {
+        matches!(self.storage, PaletteStorage::Uniform(_))
+    }
+
+    /// Returns the number of bytes used to store the voxel data of this buffer.
+    pub fn heap_size(&self) -> usize {
+        match &self.storage {
+            PaletteStorage::Uniform(_) => 0,
+            PaletteStorage::Packed { palette, words, .. } => {
+                std::mem::size_of_val(palette.as_slice()) + std::mem::size_of_val(&**words)
+            }
+        }
+    }
+
+    #[inline]
+    pub const fn shape(&self) -> &S {
+        &self.shape
+    }
+
+    fn set_linear(&mut self, linear: usize, val: V) {
+        if let PaletteStorage::Uniform(current) = self.storage {
+            if current == val {
+                return;
+            }
+
+            // promote the uniform buffer to a packed buffer with a two entries palette.
+            self.storage = PaletteStorage::Packed {
+                palette: vec![current, val],
+                bits: 1,
+                words: packed_words(self.shape.size(), 1),
+            };
+        }
+
+        let size = self.shape.size();
+        if let PaletteStorage::Packed {
+            palette,
+            bits,
+            words,
+        } = &mut self.storage
+        {
+            let mut index = palette_index(palette, val);
+
+            if index >= 1 << *bits {
+                // drop the palette entries not referenced anymore before growing the indices.
+                (*palette, *words) = compact(palette, *bits, words, size);
+                index = palette_index(palette, val);
+
+                let new_bits = bits_for_palette_len(palette.len());
+                if new_bits != *bits {
+                    *words = repack(words, *bits, new_bits, size);
+                    *bits = new_bits;
+                }
+            }
+
+            write_index(words, *bits, linear, index);
+        }
+    }
+}
+
+impl<V, S: Shape<3, Coord = u32> + Clone> From<VoxelBuffer<V, S>> for PaletteBuffer<V, S>
+where
+    V: Copy + Clone + Default + PartialEq,
+{
+    fn from(buffer: VoxelBuffer<V, S>) -> Self {
+        Self::from_buffer(&buffer)
+    }
+}
+
+/// A mutable handle to a voxel of a [`PaletteBuffer`], writing the voxel back into the buffer on drop.
+pub struct PaletteVoxelMut<'a, V, S: Shape<3, Coord = u32>>
+where
+    V: Copy + Clone + Default + PartialEq,
+{
+    buffer: &'a mut PaletteBuffer<V, S>,
+    pos: UVec3,
+    original: V,
+    value: V,
+}
+
+impl<'a, V, S: Shape<3, Coord = u32>> Deref for PaletteVoxelMut<'a, V, S>
+where
+    V: Copy + Clone + Default + PartialEq,
+{
+    type Target = V;
+
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+impl<'a, V, S: Shape<3, Coord = u32>> DerefMut for PaletteVoxelMut<'a, V, S>
+where
+    V: Copy + Clone + Default + PartialEq,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.value
+    }
+}
+
+impl<'a, V, S: Shape<3, Coord = u32>> Drop for PaletteVoxelMut<'a, V, S>
+where
+    V: Copy + Clone + Default + PartialEq,
+{
+    fn drop(&mut self) {
+        if self.value != self.original {
+            self.buffer.set_voxel(self.pos, self.value);
+        }
+    }
+}
+
+/// Returns the index of a value in the palette, inserting it if it's missing.
+fn palette_index<V: PartialEq>(palette: &mut Vec<V>, val: V) -> usize {
+    palette.iter().position(|x| *x == val).unwrap_or_else(|| {
+        palette.push(val);
+        palette.len() - 1
+    })
+}
+
+/// Returns the number of bits needed to index a palette, rounded to a power of two so indices never straddle words.
+fn bits_for_palette_len(len: usize) -> u32 {
+    let bits = usize::BITS - (len.max(2) - 1).leading_zeros();
+    bits.next_power_of_two()
+}
+
+fn packed_words(size: u32, bits: u32) -> Box<[u64]> {
+    let per_word = (u64::BITS / bits) as usize;
+    vec![0u64; (size as usize).div_ceil(per_word)].into_boxed_slice()
+}
+
+#[inline]
+fn read_index(words: &[u64], bits: u32, linear: usize) -> usize {
+    let per_word = (u64::BITS / bits) as usize;
+    let shift = (linear % per_word) as u32 * bits;
+    ((words[linear / per_word] >> shift) & ((1 << bits) - 1)) as usize
+}
+
+#[inline]
+fn write_index(words: &mut [u64], bits: u32, linear: usize, index: usize) {
+    let per_word = (u64::BITS / bits) as usize;
+    let shift = (linear % per_word) as u32 * bits;
+    let mask = ((1u64 << bits) - 1) << shift;
+    let word = &mut words[linear / per_word];
+    *word = (*word & !mask) | ((index as u64) << shift);
+}
+
+fn repack(words: &[u64], bits: u32, new_bits: u32, size: u32) -> Box<[u64]> {
+    let mut new_words = packed_words(size, new_bits);
+    (0..size as usize)
+        .for_each(|i| write_index(&mut new_words, new_bits, i, read_index(words, bits, i)));
+    new_words
+}
+
+/// Rebuilds a palette keeping only the entries still referenced, returning the new palette and indices.
+fn compact<V: Copy + PartialEq>(
+    palette: &[V],
+    bits: u32,
+    words: &[u64],
+    size: u32,
+) -> (Vec<V>, Box<[u64]>) {
+    let mut new_palette = Vec::with_capacity(palette.len());
+    let mut new_words = packed_words(size, bits);
+
+    (0..size as usize).for_each(|i| {
+        let index = palette_index(&mut new_palette, palette[read_index(words, bits, i)]);
+        write_index(&mut new_words, bits, i, index);
+    });
+
+    (new_palette, new_words)
+}
+
+#[cfg(test)]
+mod tests {
+    use ndshape::RuntimeShape;
+
+    use super::*;
+
+    fn buffer() -> PaletteBuffer<u16, RuntimeShape<u32, 3>> {
+        PaletteBuffer::new(RuntimeShape::<u32, 3>::new([4, 4, 4]), 0)
+    }
+
+    fn bits(buffer: &PaletteBuffer<u16, RuntimeShape<u32, 3>>) -> Option<u32> {
+        match &buffer.storage {
+            PaletteStorage::Uniform(_) => None,
+            PaletteStorage::Packed { bits, .. } => Some(*bits),
+        }
+    }
+
+    fn linear_pos(i: u32) -> UVec3 {
+        UVec3::new(i % 4, (i / 4) % 4, i / 16)
+    }
+
+    #[test]
+    fn uniform_buffer_grows_into_packed_buffer() {
+        let mut buffer = buffer();
+        buffer.set_voxel(UVec3::ZERO, 0);
+        assert!(buffer.is_uniform());
+        assert_eq!(buffer.heap_size(), 0);
+
+        buffer.set_voxel(UVec3::new(1, 2, 3), 7);
+        assert!(!buffer.is_uniform());
+        assert_eq!(bits(&buffer), Some(1));
+        assert_eq!(buffer.voxel_at(UVec3::new(1, 2, 3)), 7);
+        assert_eq!(buffer.voxel_at(UVec3::new(3, 2, 1)), 0);
+    }
+
+    #[test]
+    fn indices_widen_as_the_palette_grows() {
+        let mut buffer = buffer();
+
+        for value in 1..=16u16 {
+            buffer.set_voxel(linear_pos(value as u32), value);
+
+            let expected_bits = match value + 1 {
+                ..=2 => 1,
+                ..=4 => 2,
+                ..=16 => 4,
+                _ => 8,
+            };
+            assert_eq!(
+                bits(&buffer),
+                Some(expected_bits),
+                "palette of {}",
+                value + 1
+            );
+        }
+
+        for value in 1..=16 {
+            assert_eq!(buffer.voxel_at(linear_pos(value as u32)), value);
+        }
+        assert_eq!(buffer.voxel_at(UVec3::ZERO), 0);
+    }
+
+    #[test]
+    fn unreferenced_entries_are_dropped_before_widening() {
+        let mut buffer = buffer();
+        buffer.set_voxel(UVec3::ZERO, 1);
+        buffer.set_voxel(UVec3::ZERO, 0);
+
+        // the palette holds 0 and the unused 1, so 2 replaces 1 instead of widening the indices.
+        buffer.set_voxel(UVec3::X, 2);
+        assert_eq!(bits(&buffer), Some(1));
+        assert_eq!(buffer.voxel_at(UVec3::X), 2);
+        assert_eq!(buffer.voxel_at(UVec3::ZERO), 0);
+    }
+
+    #[test]
+    fn compressed_buffer_round_trips() {
+        let mut voxels = VoxelBuffer::<u16, _>::new_empty(RuntimeShape::<u32, 3>::new([4, 4, 4]));
+        voxels
+            .slice_mut()
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, voxel)| *voxel = (i % 5) as u16);
+
+        let compressed = PaletteBuffer::from_buffer(&voxels);
+        assert_eq!(bits(&compressed), Some(4));
+        assert_eq!(compressed.to_buffer().slice(), voxels.slice());
+
+        let uniform = PaletteBuffer::from_buffer(&VoxelBuffer::new(
+            RuntimeShape::<u32, 3>::new([4, 4, 4]),
+            3u16,
+        ));
+        assert!(uniform.is_uniform());
+    }
+
+    #[test]
+    fn filling_the_whole_buffer_makes_it_uniform() {
+        let mut buffer = buffer();
+        buffer.set_voxel(UVec3::ONE, 5);
+
+        buffer.fill_extent(Extent::from_min_and_shape(UVec3::ZERO, UVec3::splat(4)), 9);
+        assert!(buffer.is_uniform());
+        assert_eq!(buffer.voxel_at(UVec3::ONE), 9);
+    }
+}
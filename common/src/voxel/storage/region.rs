@@ -0,0 +1,365 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use bevy::{
+    math::IVec3,
+    prelude::{warn, Resource},
+    tasks::IoTaskPool,
+    utils::HashMap,
+};
+use ndshape::ConstShape;
+
+use crate::voxel::{ChunkShape, Voxel, CHUNK_LENGTH};
+
+use super::{PaletteBuffer, VoxelBuffer};
+
+/// Number of chunks stored along each axis of a region file.
+pub const REGION_LENGTH: i32 = 16;
+const REGION_VOLUME: usize = (REGION_LENGTH * REGION_LENGTH * REGION_LENGTH) as usize;
+
+// Each header entry is a (u32 offset, u32 length) pair.
+const HEADER_ENTRY_SIZE: u64 = 8;
+const HEADER_SIZE: u64 = REGION_VOLUME as u64 * HEADER_ENTRY_SIZE;
+
+/// Chunks encoded with 8 bit material ids and no voxel state.
+const LEGACY_CHUNK_FORMAT_VERSION: u8 = 1;
+const CHUNK_FORMAT_VERSION: u8 = 2;
+
+#[derive(Clone, Copy, Default)]
+struct RegionEntry {
+    offset: u32,
+    len: u32,
+}
+
+/// A file storing up to 16x16x16 chunks.
+///
+/// The file starts with a fixed size header mapping each chunk slot to a byte range,
+/// followed by the encoded chunk payloads.
+struct RegionFile {
+    file: File,
+    header: Box<[RegionEntry]>,
+}
+
+impl RegionFile {
+    fn open(path: PathBuf) -> io::Result<Self> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+
+        let mut header = vec![RegionEntry::default(); REGION_VOLUME].into_boxed_slice();
+
+        if file.metadata()?.len() < HEADER_SIZE {
+            file.set_len(HEADER_SIZE)?;
+        } else {
+            let mut raw = vec![0u8; HEADER_SIZE as usize];
+            file.seek(SeekFrom::Start(0))?;
+            file.read_exact(&mut raw)?;
+
+            for (entry, bytes) in header.iter_mut().zip(raw.chunks_exact(8)) {
+                entry.offset = u32::from_le_bytes(bytes[0..4].try_into().unwrap());
+                entry.len = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+            }
+        }
+
+        Ok(Self { file, header })
+    }
+
+    fn read_chunk(&mut self, slot: usize) -> io::Result<Option<Vec<u8>>> {
+        let entry = self.header[slot];
+        if entry.len == 0 {
+            return Ok(None);
+        }
+
+        let mut data = vec![0u8; entry.len as usize];
+        self.file.seek(SeekFrom::Start(entry.offset as u64))?;
+        self.file.read_exact(&mut data)?;
+        Ok(Some(data))
+    }
+
+    fn write_chunk(&mut self, slot: usize, data: &[u8]) -> io::Result<()> {
+        let mut entry = self.header[slot];
+
+        // reuse the previous location if the new payload fits, append it otherwise.
+        if data.len() > entry.len as usize {
+            entry.offset = self.file.seek(SeekFrom::End(0))? as u32;
+        }
+        entry.len = data.len() as u32;
+
+        self.file.seek(SeekFrom::Start(entry.offset as u64))?;
+        self.file.write_all(data)?;
+
+        self.file
+            .seek(SeekFrom::Start(slot as u64 * HEADER_ENTRY_SIZE))?;
+        self.file.write_all(&entry.offset.to_le_bytes())?;
+        self.file.write_all(&entry.len.to_le_bytes())?;
+
+        self.header[slot] = entry;
+        Ok(())
+    }
+}
+
+/// Returns the region coordinates and the slot index inside the region for a chunk minimum.
+fn region_slot(chunk_min: IVec3) -> (IVec3, usize) {
+    let chunk_coords = chunk_min / CHUNK_LENGTH as i32;
+    let region = IVec3::from(chunk_coords.to_array().map(|x| x.div_euclid(REGION_LENGTH)));
+    let local = IVec3::from(chunk_coords.to_array().map(|x| x.rem_euclid(REGION_LENGTH)));
+
+    (
+        region,
+        (local.x + local.y * REGION_LENGTH + local.z * REGION_LENGTH * REGION_LENGTH) as usize,
+    )
+}
+
+/// Run-length encodes a chunk buffer, each run being stored as its length, the voxel material id and state.
+pub fn encode_chunk(buffer: &VoxelBuffer<Voxel, ChunkShape>) -> Vec<u8> {
+    let mut data = vec![CHUNK_FORMAT_VERSION];
+    let mut voxels = buffer.slice().iter().peekable();
+
+    while let Some(voxel) = voxels.next() {
+        let mut run = 1u16;
+        while run < u16::MAX && voxels.next_if_eq(&voxel).is_some() {
+            run += 1;
+        }
+        data.extend_from_slice(&run.to_le_bytes());
+        data.extend_from_slice(&voxel.id.to_le_bytes());
+        data.push(voxel.state);
+    }
+
+    data
+}
+
+/// Decodes a chunk buffer encoded with [`encode_chunk`], returns `None` if the data is malformed.
+/// Chunks saved with 8 bit material ids and no state are still read back.
+pub fn decode_chunk(data: &[u8]) -> Option<VoxelBuffer<Voxel, ChunkShape>> {
+    let (version, runs) = data.split_first()?;
+    let run_size = match *version {
+        LEGACY_CHUNK_FORMAT_VERSION => 3,
+        CHUNK_FORMAT_VERSION => 5,
+        _ => return None,
+    };
+
+    if runs.len() % run_size != 0 {
+        return None;
+    }
+
+    let mut buffer = VoxelBuffer::<Voxel, ChunkShape>::new_empty(ChunkShape {});
+    let mut cursor = 0usize;
+
+    for run in runs.chunks_exact(run_size) {
+        let len = u16::from_le_bytes([run[0], run[1]]) as usize;
+        let voxel = match run {
+            [_, _, id] => Voxel::new(*id as u16),
+            [_, _, id_low, id_high, state] => {
+                Voxel::new(u16::from_le_bytes([*id_low, *id_high])).with_state(*state)
+            }
+            _ => unreachable!(),
+        };
+
+        buffer
+            .slice_mut()
+            .get_mut(cursor..cursor + len)?
+            .fill(voxel);
+        cursor += len;
+    }
+
+    (cursor == ChunkShape::SIZE as usize).then_some(buffer)
+}
+
+/// Persistent storage for chunk data, backed by region files in a world directory.
+///
+/// Chunks queued for saving are kept in memory until they are flushed to disk, so that a chunk
+/// which is reloaded while its save is in flight is never read back stale.
+#[derive(Resource, Clone)]
+pub struct RegionStore {
+    root: PathBuf,
+    regions: Arc<Mutex<HashMap<IVec3, RegionFile>>>,
+    pending: Arc<Mutex<HashMap<IVec3, Arc<PaletteBuffer<Voxel, ChunkShape>>>>>,
+    /// Whether a background flush is running, so a slow disk never piles up flush tasks.
+    flushing: Arc<AtomicBool>,
+}
+
+impl RegionStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            regions: Default::default(),
+            pending: Default::default(),
+            flushing: Default::default(),
+        }
+    }
+
+    /// Loads the chunk at the specified minimum if it was previously saved.
+    pub fn load(&self, chunk_min: IVec3) -> Option<PaletteBuffer<Voxel, ChunkShape>> {
+        if let Some(buffer) = self.pending.lock().unwrap().get(&chunk_min) {
+            return Some((**buffer).clone());
+        }
+
+        let (region, slot) = region_slot(chunk_min);
+        let mut regions = self.regions.lock().unwrap();
+
+        match self
+            .region_file(&mut regions, region)
+            .and_then(|file| file.read_chunk(slot))
+        {
+            Ok(data) => data.and_then(|data| {
+                let buffer = decode_chunk(&data);
+                if buffer.is_none() {
+                    warn!("Discarding corrupted chunk data at {}", chunk_min);
+                }
+                buffer.map(PaletteBuffer::from)
+            }),
+            Err(err) => {
+                warn!("Failed to read chunk {} from disk: {}", chunk_min, err);
+                None
+            }
+        }
+    }
+
+    /// Queues the chunk at the specified minimum to be written on the next [`RegionStore::flush`].
+    pub fn queue_save(&self, chunk_min: IVec3, buffer: PaletteBuffer<Voxel, ChunkShape>) {
+        self.pending
+            .lock()
+            .unwrap()
+            .insert(chunk_min, Arc::new(buffer));
+    }
+
+    /// Returns whether there are chunks waiting to be written to disk.
+    pub fn has_pending(&self) -> bool {
+        !self.pending.lock().unwrap().is_empty()
+    }
+
+    /// Writes all the queued chunks to their region files.
+    pub fn flush(&self) {
+        let mut regions = self.regions.lock().unwrap();
+        let queued: Vec<_> = self
+            .pending
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(key, buffer)| (*key, buffer.clone()))
+            .collect();
+
+        for (chunk_min, buffer) in queued {
+            let (region, slot) = region_slot(chunk_min);
+
+            if let Err(err) = self
+                .region_file(&mut regions, region)
+                .and_then(|file| file.write_chunk(slot, &encode_chunk(&buffer.to_buffer())))
+            {
+                warn!("Failed to write chunk {} to disk: {}", chunk_min, err);
+                continue;
+            }
+
+            // only drop the pending entry if it wasn't replaced by a newer save in the meantime.
+            let mut pending = self.pending.lock().unwrap();
+            if pending
+                .get(&chunk_min)
+                .is_some_and(|current| Arc::ptr_eq(current, &buffer))
+            {
+                pending.remove(&chunk_min);
+            }
+        }
+    }
+
+    /// Writes the queued chunks to their region files on the IO task pool,
+    /// unless there is nothing to write or a previous flush is still running.
+    pub fn flush_in_background(&self) {
+        if !self.has_pending() || self.flushing.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        let region_store = self.clone();
+        IoTaskPool::get()
+            .spawn(async move {
+                region_store.flush();
+                region_store.flushing.store(false, Ordering::Release);
+            })
+            .detach();
+    }
+
+    fn region_file<'a>(
+        &self,
+        regions: &'a mut HashMap<IVec3, RegionFile>,
+        region: IVec3,
+    ) -> io::Result<&'a mut RegionFile> {
+        if !regions.contains_key(&region) {
+            fs::create_dir_all(&self.root)?;
+            let path = self
+                .root
+                .join(format!("r.{}.{}.{}.region", region.x, region.y, region.z));
+            regions.insert(region, RegionFile::open(path)?);
+        }
+
+        Ok(regions.get_mut(&region).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_chunk() -> VoxelBuffer<Voxel, ChunkShape> {
+        let mut buffer = VoxelBuffer::<Voxel, ChunkShape>::new_empty(ChunkShape {});
+        for (i, voxel) in buffer.slice_mut().iter_mut().enumerate() {
+            *voxel = match i % 7 {
+                0 => Voxel::new(300).with_state(4),
+                1 | 2 => Voxel::new(3),
+                _ => Voxel::EMPTY_VOXEL,
+            };
+        }
+        buffer
+    }
+
+    #[test]
+    fn encoded_chunk_round_trips() {
+        let buffer = sample_chunk();
+        let decoded = decode_chunk(&encode_chunk(&buffer)).unwrap();
+
+        assert_eq!(buffer.slice(), decoded.slice());
+    }
+
+    #[test]
+    fn uniform_chunk_encodes_to_a_single_run() {
+        let buffer = VoxelBuffer::<Voxel, ChunkShape>::new(ChunkShape {}, Voxel::new(2));
+        let data = encode_chunk(&buffer);
+
+        assert_eq!(data.len(), 1 + 5);
+        assert_eq!(decode_chunk(&data).unwrap().slice(), buffer.slice());
+    }
+
+    #[test]
+    fn legacy_chunk_is_decoded() {
+        let half = (ChunkShape::SIZE / 2) as u16;
+        let mut data = vec![LEGACY_CHUNK_FORMAT_VERSION];
+        data.extend_from_slice(&half.to_le_bytes());
+        data.push(5);
+        data.extend_from_slice(&half.to_le_bytes());
+        data.push(0);
+
+        let decoded = decode_chunk(&data).unwrap();
+        let (low, high) = decoded.slice().split_at(half as usize);
+
+        assert!(low.iter().all(|voxel| *voxel == Voxel::new(5)));
+        assert!(high.iter().all(Voxel::is_empty));
+    }
+
+    #[test]
+    fn malformed_chunk_is_rejected() {
+        let data = encode_chunk(&sample_chunk());
+
+        assert!(decode_chunk(&[]).is_none());
+        assert!(decode_chunk(&[CHUNK_FORMAT_VERSION + 1]).is_none());
+        assert!(decode_chunk(&data[..data.len() - 1]).is_none());
+        assert!(decode_chunk(&data[..data.len() - 5]).is_none());
+    }
+}
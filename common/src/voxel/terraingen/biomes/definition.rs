@@ -0,0 +1,267 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    math::{IVec2, IVec3, Vec2, Vec3},
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+
+use crate::voxel::{
+    materials::voxel_by_name,
+    sdf,
+    terraingen::{
+        climate::ClimateRange,
+        common::CaveParameters,
+        noise,
+        structures::{Structure, STRUCTURE_MAX_DEPTH, STRUCTURE_MAX_REACH},
+        WorldSeed,
+    },
+    Voxel,
+};
+
+use super::{LayeredBiomeTerrainGenerator, SurfaceRelief};
+
+/// A material referred to by name in a definition file.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(try_from = "String")]
+pub struct MaterialName(pub Voxel);
+
+impl TryFrom<String> for MaterialName {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        voxel_by_name(&name)
+            .map(Self)
+            .ok_or_else(|| format!("unknown material `{name}`"))
+    }
+}
+
+/// A shape a decoration is made of, see [`sdf`] for the matching distance functions.
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub enum DecorationShape {
+    Sphere {
+        radius: f32,
+    },
+    Box {
+        half_extents: Vec3,
+    },
+    /// A vertical cylinder centered on its offset.
+    Cylinder {
+        radius: f32,
+        half_height: f32,
+    },
+    /// A vertical capsule rising from its offset.
+    Capsule {
+        radius: f32,
+        height: f32,
+    },
+    /// A vertical cone whose base lies at its offset.
+    Cone {
+        radius: f32,
+        height: f32,
+    },
+}
+
+impl DecorationShape {
+    fn distance(&self, p: Vec3) -> f32 {
+        match *self {
+            Self::Sphere { radius } => sdf::sdf_sphere(p, radius),
+            Self::Box { half_extents } => sdf::sdf_box(p, half_extents),
+            Self::Cylinder {
+                radius,
+                half_height,
+            } => sdf::sdf_capped_cylinder(p, radius, half_height),
+            Self::Capsule { radius, height } => sdf::sdf_v_capsule(p, height, radius),
+            Self::Cone { radius, height } => sdf::sdf_vcone(p, radius, height),
+        }
+    }
+
+    /// Returns the minimum and maximum corners of the box bounding the shape.
+    fn bounds(&self) -> (Vec3, Vec3) {
+        match *self {
+            Self::Sphere { radius } => (Vec3::splat(-radius), Vec3::splat(radius)),
+            Self::Box { half_extents } => (-half_extents, half_extents),
+            Self::Cylinder {
+                radius,
+                half_height,
+            } => (
+                Vec3::new(-radius, -half_height, -radius),
+                Vec3::new(radius, half_height, radius),
+            ),
+            Self::Capsule { radius, height } => (
+                Vec3::splat(-radius),
+                Vec3::new(radius, height + radius, radius),
+            ),
+            Self::Cone { radius, height } => (
+                Vec3::new(-radius, 0.0, -radius),
+                Vec3::new(radius, height, radius),
+            ),
+        }
+    }
+}
+
+/// A part of a decoration, filled with a single material.
+#[derive(Clone, Debug, Deserialize)]
+pub struct DecorationPart {
+    pub shape: DecorationShape,
+    /// Offset of the shape from the surface voxel the decoration is anchored on.
+    #[serde(default)]
+    pub offset: Vec3,
+    pub material: MaterialName,
+}
+
+/// A structure scattered over the surface of a biome, where overlapping parts are filled with the first one listed.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Decoration {
+    /// Share of the surface columns this decoration is anchored on.
+    pub spawn_chance: f32,
+    pub parts: Vec<DecorationPart>,
+}
+
+impl Structure for Decoration {
+    fn bounds(&self) -> (IVec3, IVec3) {
+        self.parts
+            .iter()
+            .map(|part| {
+                let (min, max) = part.shape.bounds();
+                (part.offset + min, part.offset + max)
+            })
+            .reduce(|(min_a, max_a), (min_b, max_b)| (min_a.min(min_b), max_a.max(max_b)))
+            .map_or((IVec3::ZERO, IVec3::ZERO), |(min, max)| {
+                (min.floor().as_ivec3(), max.ceil().as_ivec3() + IVec3::ONE)
+            })
+    }
+
+    fn voxel_at(&self, offset: Vec3) -> Option<Voxel> {
+        self.parts
+            .iter()
+            .find(|part| part.shape.distance(offset - part.offset) < 0.0)
+            .map(|part| part.material.0)
+    }
+}
+
+/// A biome described by a `.biome.ron` asset file rather than by code.
+#[derive(Clone, Debug, Deserialize, TypeUuid)]
+#[uuid = "4d1b4c87-3f0e-4a52-9d49-6a2b0f5c7e13"]
+pub struct BiomeDefinition {
+    pub name: String,
+    pub climate: ClimateRange,
+    /// Materials of the layers laid on top of the terrain, from the surface down.
+    /// The last material is repeated down to `num_layers`.
+    pub strata: Vec<MaterialName>,
+    /// Materials of the layers covering the shores and the water beds, the regular strata are used when empty.
+    #[serde(default)]
+    pub beach_strata: Vec<MaterialName>,
+    pub num_layers: u32,
+    #[serde(default)]
+    pub relief: SurfaceRelief,
+    #[serde(default)]
+    pub caves: CaveParameters,
+    #[serde(default)]
+    pub decorations: Vec<Decoration>,
+}
+
+impl BiomeDefinition {
+    /// Checks the definition can be turned into a terrain generator.
+    fn validate(&self) -> Result<(), String> {
+        if self.strata.is_empty() {
+            return Err(format!("biome `{}` has no strata", self.name));
+        }
+
+        for decoration in self.decorations.iter() {
+            let (min, max) = decoration.bounds();
+            let reach = (-min.x).max(-min.z).max(max.x - 1).max(max.z - 1);
+
+            if reach > STRUCTURE_MAX_REACH {
+                return Err(format!(
+                    "a decoration of biome `{}` reaches {reach} voxels away from its anchor, more than the {STRUCTURE_MAX_REACH} allowed",
+                    self.name
+                ));
+            }
+
+            if -min.y > STRUCTURE_MAX_DEPTH {
+                return Err(format!(
+                    "a decoration of biome `{}` reaches {} voxels below its anchor, more than the {STRUCTURE_MAX_DEPTH} allowed",
+                    self.name, -min.y
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Picks the material of a layer in a strata list, repeating the last material below its end.
+fn strata_layer(strata: &[MaterialName], layer: u32) -> Voxel {
+    strata
+        .get(layer as usize)
+        .or(strata.last())
+        .map_or(Voxel::EMPTY_VOXEL, |material| material.0)
+}
+
+impl LayeredBiomeTerrainGenerator for BiomeDefinition {
+    fn fill_strata(&self, layer: u32) -> Voxel {
+        strata_layer(&self.strata, layer)
+    }
+
+    fn beach_strata(&self, layer: u32) -> Voxel {
+        if self.beach_strata.is_empty() {
+            self.fill_strata(layer)
+        } else {
+            strata_layer(&self.beach_strata, layer)
+        }
+    }
+
+    fn caves(&self) -> CaveParameters {
+        self.caves
+    }
+
+    fn relief(&self) -> SurfaceRelief {
+        self.relief
+    }
+
+    fn num_layers(&self) -> u32 {
+        self.num_layers
+    }
+
+    fn decoration_at(&self, column: IVec2, seed: WorldSeed) -> Option<Box<dyn Structure>> {
+        let mut roll = 1.0
+            - noise::rand2to1(
+                column.as_vec2() * 0.1 + seed.offset(),
+                Vec2::new(12.989, 78.233),
+            )
+            .abs();
+
+        self.decorations
+            .iter()
+            .find(|decoration| {
+                roll -= decoration.spawn_chance;
+                roll < 0.0
+            })
+            .map(|decoration| Box::new(decoration.clone()) as Box<dyn Structure>)
+    }
+}
+
+/// Loads the `.biome.ron` biome definition files.
+#[derive(Default)]
+pub struct BiomeDefinitionLoader;
+
+impl AssetLoader for BiomeDefinitionLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let definition = ron::de::from_bytes::<BiomeDefinition>(bytes)?;
+            definition.validate().map_err(bevy::asset::Error::msg)?;
+
+            load_context.set_default_asset(LoadedAsset::new(definition));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["biome.ron"]
+    }
+}
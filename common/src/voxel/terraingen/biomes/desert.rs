@@ -0,0 +1,101 @@
+use bevy::math::{IVec2, IVec3, Vec2, Vec3};
+
+use crate::voxel::{
+    material::VoxelMaterial,
+    materials::{Cactus, Sand, Sandstone},
+    sdf,
+    terraingen::{
+        common::CaveParameters,
+        noise,
+        ores::{OreDistribution, DEFAULT_ORES},
+        structures::Structure,
+        WorldSeed,
+    },
+    Voxel,
+};
+
+use super::{LayeredBiomeTerrainGenerator, SurfaceRelief};
+
+pub struct BasicDesertBiomeTerrainGenerator;
+
+/// Deserts are poor in coal but rich in gold.
+const DESERT_ORES: [OreDistribution; 4] = [
+    OreDistribution {
+        veins_per_chunk: 3.0,
+        ..DEFAULT_ORES[0]
+    },
+    DEFAULT_ORES[1],
+    OreDistribution {
+        max_height: 80,
+        veins_per_chunk: 3.0,
+        ..DEFAULT_ORES[2]
+    },
+    DEFAULT_ORES[3],
+];
+
+impl LayeredBiomeTerrainGenerator for BasicDesertBiomeTerrainGenerator {
+    fn fill_strata(&self, layer: u32) -> Voxel {
+        match layer {
+            0..=5 => Sand::into_voxel(),
+            _ => Sandstone::into_voxel(),
+        }
+    }
+
+    // the dunes simply run down to the water.
+    fn beach_strata(&self, layer: u32) -> Voxel {
+        self.fill_strata(layer)
+    }
+
+    // wide sandstone caverns but few tunnels.
+    fn caves(&self) -> CaveParameters {
+        CaveParameters {
+            cheese_threshold: 0.38,
+            worm_radius: 0.05,
+            ..Default::default()
+        }
+    }
+
+    // low and flat dunes.
+    fn relief(&self) -> SurfaceRelief {
+        SurfaceRelief {
+            offset: -4.0,
+            scale: 0.5,
+        }
+    }
+
+    fn ore_distribution(&self) -> &'static [OreDistribution] {
+        &DESERT_ORES
+    }
+
+    fn decoration_at(&self, column: IVec2, seed: WorldSeed) -> Option<Box<dyn Structure>> {
+        let cacti_spawn_chance = noise::rand2to1(
+            column.as_vec2() * 0.1 + seed.offset(),
+            Vec2::new(12.989, 78.233),
+        );
+
+        if cacti_spawn_chance > 0.992 {
+            let size = ((cacti_spawn_chance - 0.992) * 2000.0) as u32 + 2;
+            return Some(Box::new(Cacti { size }));
+        }
+
+        None
+    }
+}
+
+/// A vertical cactus of the specified height.
+struct Cacti {
+    size: u32,
+}
+
+impl Structure for Cacti {
+    fn bounds(&self) -> (IVec3, IVec3) {
+        (
+            IVec3::new(-2, -1, -2),
+            IVec3::new(3, self.size as i32 + 4, 3),
+        )
+    }
+
+    fn voxel_at(&self, offset: Vec3) -> Option<Voxel> {
+        (sdf::sdf_v_capsule(offset - Vec3::Y, self.size as f32, 1.5) < 0.0).then(Cactus::into_voxel)
+    }
+}
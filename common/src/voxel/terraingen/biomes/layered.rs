@@ -0,0 +1,118 @@
+use std::ops::Div;
+
+use bevy::math::{IVec2, IVec3};
+use ilattice::glam::UVec2;
+
+use crate::voxel::{
+    material::VoxelMaterial,
+    materials::{Dirt, Grass, Sand},
+    storage::VoxelBuffer,
+    terraingen::{
+        common::CaveParameters,
+        ores::{OreDistribution, DEFAULT_ORES},
+        structures::Structure,
+        water::BEACH_HEIGHT,
+        WorldSeed,
+    },
+    ChunkShape, Voxel, CHUNK_LENGTH,
+};
+
+use super::{BiomeTerrainGenerator, SurfaceRelief};
+
+/// A biome terrain generator that applies a set of layers on top of the terrain.
+pub trait LayeredBiomeTerrainGenerator: BiomeTerrainGenerator {
+    /// The height function to use for applying the biome material layers on top of the terrain.
+    fn fill_strata(&self, layer: u32) -> Voxel {
+        match layer {
+            0..=1 => Grass::into_voxel(),
+            _ => Dirt::into_voxel(),
+        }
+    }
+
+    /// The material layers covering the shores and the beds of the seas, lakes and rivers.
+    fn beach_strata(&self, layer: u32) -> Voxel {
+        match layer {
+            0..=3 => Sand::into_voxel(),
+            _ => Dirt::into_voxel(),
+        }
+    }
+
+    /// The parameters of the caves carved under this biome.
+    fn caves(&self) -> CaveParameters {
+        CaveParameters::default()
+    }
+
+    /// The ores found under this biome.
+    fn ore_distribution(&self) -> &'static [OreDistribution] {
+        &DEFAULT_ORES
+    }
+
+    /// How this biome reshapes the terrain.
+    fn relief(&self) -> SurfaceRelief {
+        SurfaceRelief::default()
+    }
+
+    /// Numbers of material layers to apply on top of the terrain
+    fn num_layers(&self) -> u32 {
+        8
+    }
+
+    /// Returns the decoration (e.g. a tree) anchored on the surface of the specified world column, if any.
+    fn decoration_at(&self, _column: IVec2, _seed: WorldSeed) -> Option<Box<dyn Structure>> {
+        None
+    }
+}
+
+impl<T: LayeredBiomeTerrainGenerator> BiomeTerrainGenerator for T {
+    fn cave_parameters(&self) -> CaveParameters {
+        self.caves()
+    }
+
+    fn ores(&self) -> &'static [OreDistribution] {
+        self.ore_distribution()
+    }
+
+    fn surface_relief(&self) -> SurfaceRelief {
+        self.relief()
+    }
+
+    fn carve_column(
+        &self,
+        chunk_key: IVec3,
+        column: UVec2,
+        height: u32,
+        sea_level: u32,
+        buffer: &mut VoxelBuffer<Voxel, ChunkShape>,
+    ) {
+        let beach = height <= sea_level + BEACH_HEIGHT;
+
+        // we only want to apply surface layer decoration on top of the surface chunk
+        if height.div(CHUNK_LENGTH) == (chunk_key.y as u32).div(CHUNK_LENGTH) {
+            let local_height = height.rem_euclid(CHUNK_LENGTH);
+
+            for h in 0..=self.num_layers() {
+                let remaining_height = local_height.checked_sub(h);
+
+                if let Some(uh) = remaining_height {
+                    // leave the cave openings carved into the surface, the top layer rests on the carved rock.
+                    let support = if h == 0 { uh.checked_sub(1) } else { Some(uh) };
+                    let carved = support.is_some_and(|y| {
+                        buffer.voxel_at([column.x, y, column.y].into()) == Voxel::EMPTY_VOXEL
+                    });
+
+                    if !carved {
+                        *buffer.voxel_at_mut([column.x, uh, column.y].into()) = if beach {
+                            self.beach_strata(h)
+                        } else {
+                            self.fill_strata(h)
+                        };
+                    }
+                }
+            }
+        }
+    }
+
+    fn structure_at(&self, column: IVec2, seed: WorldSeed) -> Option<Box<dyn Structure>> {
+        self.decoration_at(column, seed)
+    }
+}
@@ -0,0 +1,79 @@
+use serde::Deserialize;
+
+use crate::voxel::{storage::VoxelBuffer, ChunkShape, Voxel};
+
+use super::{common::CaveParameters, ores::OreDistribution, structures::Structure, WorldSeed};
+
+mod layered;
+use bevy::math::{IVec2, IVec3};
+use ilattice::glam::UVec2;
+pub use layered::*;
+
+mod plains;
+pub use plains::*;
+
+mod desert;
+pub use desert::*;
+
+mod snowy_plains;
+pub use snowy_plains::*;
+
+mod definition;
+pub use definition::*;
+
+/// How a biome reshapes the terrain height given by the heightmap noise.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default)]
+pub struct SurfaceRelief {
+    /// Height added to the terrain.
+    pub offset: f32,
+    /// Factor applied to the terrain height variations around its base height.
+    pub scale: f32,
+}
+
+impl Default for SurfaceRelief {
+    fn default() -> Self {
+        Self {
+            offset: 0.0,
+            scale: 1.0,
+        }
+    }
+}
+
+/// A trait representing a terrain generator for a biome.
+/// A biome can be defined as a collection of features that are applied on top of the terrain.
+pub trait BiomeTerrainGenerator: 'static + Sync + Send {
+    /// The parameters of the caves carved under this biome.
+    fn cave_parameters(&self) -> CaveParameters;
+
+    /// The ores found under this biome.
+    fn ores(&self) -> &'static [OreDistribution];
+
+    /// How this biome reshapes the terrain, blended with the neighbouring biomes near borders.
+    fn surface_relief(&self) -> SurfaceRelief;
+
+    /// Carve the surface of a column of the chunk, whose terrain reaches `height`, using the materials for the biome.
+    /// Columns lying close to or below `sea_level` are covered with the beach materials of the biome.
+    fn carve_column(
+        &self,
+        chunk_key: IVec3,
+        column: UVec2,
+        height: u32,
+        sea_level: u32,
+        buffer: &mut VoxelBuffer<Voxel, ChunkShape>,
+    );
+
+    /// Returns the structure (e.g. a tree) anchored on the surface of the specified world column, if any.
+    fn structure_at(&self, column: IVec2, seed: WorldSeed) -> Option<Box<dyn Structure>>;
+}
+
+/// Utility trait for boxing biome generators.
+pub trait IntoBoxedTerrainGenerator: BiomeTerrainGenerator + Sized {
+    fn into_boxed_generator(self) -> Box<Self>;
+}
+
+impl<T: BiomeTerrainGenerator> IntoBoxedTerrainGenerator for T {
+    fn into_boxed_generator(self) -> Box<Self> {
+        Box::new(self)
+    }
+}
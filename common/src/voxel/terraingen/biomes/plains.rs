@@ -0,0 +1,38 @@
+use crate::voxel::{
+    material::VoxelMaterial,
+    materials::{Dirt, Grass, Leaves, Wood},
+    terraingen::{
+        noise,
+        structures::{Structure, Tree},
+        WorldSeed,
+    },
+    Voxel,
+};
+use bevy::math::{IVec2, Vec2};
+
+use super::LayeredBiomeTerrainGenerator;
+
+pub struct BasicPlainsBiomeTerrainGenerator;
+
+impl LayeredBiomeTerrainGenerator for BasicPlainsBiomeTerrainGenerator {
+    fn fill_strata(&self, layer: u32) -> Voxel {
+        match layer {
+            0..=1 => Grass::into_voxel(),
+            _ => Dirt::into_voxel(),
+        }
+    }
+
+    fn decoration_at(&self, column: IVec2, seed: WorldSeed) -> Option<Box<dyn Structure>> {
+        let spawn_chance = noise::rand2to1(
+            column.as_vec2() * 0.1 + seed.offset(),
+            Vec2::new(12.989, 78.233),
+        );
+
+        (spawn_chance > 0.981).then(|| {
+            Box::new(Tree {
+                trunk: Wood::into_voxel(),
+                leaves: Leaves::into_voxel(),
+            }) as Box<dyn Structure>
+        })
+    }
+}
@@ -0,0 +1,74 @@
+use crate::voxel::{
+    material::VoxelMaterial,
+    materials::{Dirt, Grass, PineLeaves, PineWood, Snow},
+    terraingen::{
+        common::CaveParameters,
+        noise,
+        ores::{OreDistribution, DEFAULT_ORES},
+        structures::{PineTree, Structure},
+        WorldSeed,
+    },
+    Voxel,
+};
+use bevy::math::{IVec2, Vec2};
+
+use super::{LayeredBiomeTerrainGenerator, SurfaceRelief};
+
+pub struct BasicSnowyPlainsBiomeTerrainGenerator;
+
+/// Mountains of iron under the snow.
+const SNOWY_PLAINS_ORES: [OreDistribution; 4] = [
+    DEFAULT_ORES[0],
+    OreDistribution {
+        max_height: 130,
+        veins_per_chunk: 7.0,
+        ..DEFAULT_ORES[1]
+    },
+    DEFAULT_ORES[2],
+    DEFAULT_ORES[3],
+];
+
+impl LayeredBiomeTerrainGenerator for BasicSnowyPlainsBiomeTerrainGenerator {
+    fn fill_strata(&self, layer: u32) -> Voxel {
+        match layer {
+            0 => Snow::into_voxel(),
+            1..=2 => Grass::into_voxel(),
+            _ => Dirt::into_voxel(),
+        }
+    }
+
+    // frozen ground, caves stay deep and tunnels rarely reach the surface.
+    fn caves(&self) -> CaveParameters {
+        CaveParameters {
+            cheese_surface_depth: 24,
+            worm_radius: 0.05,
+            ..Default::default()
+        }
+    }
+
+    // high rolling hills.
+    fn relief(&self) -> SurfaceRelief {
+        SurfaceRelief {
+            offset: 10.0,
+            scale: 1.5,
+        }
+    }
+
+    fn ore_distribution(&self) -> &'static [OreDistribution] {
+        &SNOWY_PLAINS_ORES
+    }
+
+    fn decoration_at(&self, column: IVec2, seed: WorldSeed) -> Option<Box<dyn Structure>> {
+        let spawn_chance = noise::rand2to1(
+            column.as_vec2() * 0.1 + seed.offset(),
+            Vec2::new(12.989, 78.233),
+        );
+
+        (spawn_chance > 0.981).then(|| {
+            Box::new(PineTree {
+                trunk: PineWood::into_voxel(),
+                leaves: PineLeaves::into_voxel(),
+            }) as Box<dyn Structure>
+        })
+    }
+}
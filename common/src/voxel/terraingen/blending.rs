@@ -0,0 +1,93 @@
+use bevy::math::IVec2;
+
+/// Distance between two samples of the biome grid.
+const BIOME_GRID_STEP: i32 = 4;
+
+/// Radius around a column within which the neighbouring biomes are blended.
+pub const BIOME_BLEND_RADIUS: i32 = 16;
+
+/// The weights of the biomes surrounding a column, indexed by biome and summing to one.
+pub type BiomeWeights = Vec<(usize, f32)>;
+
+/// The biomes sampled on a world aligned grid covering a chunk and its surroundings.
+/// Since the grid is aligned on the world, the weights of a column are the same whatever chunk they are computed for.
+pub struct BiomeGrid {
+    origin: IVec2,
+    size: i32,
+    biomes: Vec<usize>,
+}
+
+impl BiomeGrid {
+    /// Samples the biomes over the columns of the chunk at `chunk_min`, extended by `margin` columns on each side
+    /// plus the blend radius.
+    pub fn new(
+        chunk_min: IVec2,
+        chunk_len: i32,
+        margin: i32,
+        biome_at: impl Fn(IVec2) -> usize,
+    ) -> Self {
+        let extent = margin + BIOME_BLEND_RADIUS;
+        let origin = (chunk_min - extent)
+            .to_array()
+            .map(|c| c.div_euclid(BIOME_GRID_STEP) * BIOME_GRID_STEP);
+        let origin = IVec2::from(origin);
+        let size = (chunk_min.x + chunk_len + extent - origin.x) / BIOME_GRID_STEP + 2;
+
+        let biomes = (0..size)
+            .flat_map(|z| (0..size).map(move |x| IVec2::new(x, z)))
+            .map(|point| biome_at(origin + point * BIOME_GRID_STEP))
+            .collect();
+
+        Self {
+            origin,
+            size,
+            biomes,
+        }
+    }
+
+    /// Returns the biome sampled at a grid point.
+    pub fn biome_at(&self, point: IVec2) -> usize {
+        let index = (point - self.origin) / BIOME_GRID_STEP;
+        self.biomes[(index.y * self.size + index.x) as usize]
+    }
+
+    /// Returns the biome sampled at the grid point closest to a column.
+    pub fn nearest_biome(&self, column: IVec2) -> usize {
+        let point = column
+            .to_array()
+            .map(|c| (c + BIOME_GRID_STEP / 2).div_euclid(BIOME_GRID_STEP) * BIOME_GRID_STEP);
+        self.biome_at(IVec2::from(point))
+    }
+
+    /// Computes the weights of the biomes sampled within the blend radius of a column, closer samples weighting more.
+    pub fn weights(&self, column: IVec2) -> BiomeWeights {
+        let mut weights: BiomeWeights = Vec::with_capacity(2);
+        let first = (column - BIOME_BLEND_RADIUS)
+            .to_array()
+            .map(|c| (c + BIOME_GRID_STEP - 1).div_euclid(BIOME_GRID_STEP) * BIOME_GRID_STEP);
+        let radius_squared = BIOME_BLEND_RADIUS.pow(2) as f32;
+
+        for z in (first[1]..=column.y + BIOME_BLEND_RADIUS).step_by(BIOME_GRID_STEP as usize) {
+            for x in (first[0]..=column.x + BIOME_BLEND_RADIUS).step_by(BIOME_GRID_STEP as usize) {
+                let point = IVec2::new(x, z);
+                let offset = point - column;
+                let distance_squared = offset.dot(offset) as f32;
+                if distance_squared >= radius_squared {
+                    continue;
+                }
+
+                let weight = (1.0f32 - distance_squared / radius_squared).powi(2);
+                let biome = self.biome_at(point);
+                match weights.iter_mut().find(|(index, _)| *index == biome) {
+                    Some((_, total)) => *total += weight,
+                    None => weights.push((biome, weight)),
+                }
+            }
+        }
+
+        let total: f32 = weights.iter().map(|(_, weight)| weight).sum();
+        weights.iter_mut().for_each(|(_, weight)| *weight /= total);
+        weights.sort_unstable_by_key(|(index, _)| *index);
+        weights
+    }
+}
@@ -0,0 +1,87 @@
+use std::ops::Range;
+
+use bevy::math::IVec2;
+use noise::{Fbm, MultiFractal, NoiseFn, SuperSimplex};
+use serde::Deserialize;
+
+use super::{
+    noise::{column_height, heightmap_noise, TERRAIN_BASE_HEIGHT, TERRAIN_HEIGHT_AMPLITUDE},
+    WorldSeed,
+};
+
+/// Temperature drop of the terrain reaching the full height amplitude above the base height.
+const ALTITUDE_COOLING: f32 = 0.6;
+
+/// Share of the height amplitude above the base height from which the terrain is snowy whatever its climate,
+/// reached by about a tenth of the terrain.
+const SNOW_LINE: f32 = 0.7;
+
+/// Temperature of the terrain above the [`SNOW_LINE`] at most, within the climate of the snowy biomes.
+const SNOW_LINE_TEMPERATURE: f32 = -0.5;
+
+/// The climate of a world column, both values lying within [-1, 1].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Climate {
+    pub temperature: f32,
+    pub humidity: f32,
+}
+
+/// The climate a biome is found in.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ClimateRange {
+    pub temperature: Range<f32>,
+    pub humidity: Range<f32>,
+}
+
+impl ClimateRange {
+    /// Returns how far a climate is from this range, zero if it lies within it.
+    pub fn distance(&self, climate: Climate) -> f32 {
+        let outside =
+            |range: &Range<f32>, value: f32| (range.start - value).max(value - range.end).max(0.0);
+
+        outside(&self.temperature, climate.temperature)
+            .hypot(outside(&self.humidity, climate.humidity))
+    }
+}
+
+/// Samples the temperature and humidity noise fields of a world.
+pub struct ClimateNoise {
+    temperature: Fbm<SuperSimplex>,
+    humidity: Fbm<SuperSimplex>,
+    height: Fbm<SuperSimplex>,
+}
+
+impl ClimateNoise {
+    pub fn new(seed: WorldSeed) -> Self {
+        let climate_noise = |seed: u32| {
+            Fbm::<SuperSimplex>::new(seed)
+                .set_octaves(3)
+                .set_frequency(0.0008)
+        };
+
+        Self {
+            temperature: climate_noise(seed.0.wrapping_add(3)),
+            humidity: climate_noise(seed.0.wrapping_add(4)),
+            height: heightmap_noise(seed),
+        }
+    }
+
+    /// Returns the climate of a world column, colder the higher the terrain is, and snowy above the snow line.
+    pub fn climate_at(&self, column: IVec2) -> Climate {
+        let point = column.as_dvec2().to_array();
+        let altitude = ((column_height(&self.height, column) - TERRAIN_BASE_HEIGHT)
+            / TERRAIN_HEIGHT_AMPLITUDE)
+            .max(0.0);
+
+        let mut temperature =
+            self.temperature.get(point) as f32 * 1.5 - altitude * ALTITUDE_COOLING;
+        if altitude >= SNOW_LINE {
+            temperature = temperature.min(SNOW_LINE_TEMPERATURE);
+        }
+
+        Climate {
+            temperature: temperature.clamp(-1.0, 1.0),
+            humidity: (self.humidity.get(point) as f32 * 1.5).clamp(-1.0, 1.0),
+        }
+    }
+}
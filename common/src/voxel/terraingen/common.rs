@@ -0,0 +1,151 @@
+use bevy::math::IVec3;
+use ilattice::{glam::UVec2, glam::UVec3, prelude::Extent};
+use noise::MultiFractal;
+use serde::Deserialize;
+
+use crate::voxel::{
+    material::VoxelMaterial,
+    materials::{Bedrock, Rock},
+    storage::VoxelBuffer,
+    ChunkShape, Voxel, CHUNK_LENGTH, CHUNK_LENGTH_U,
+};
+
+use super::{
+    noise::{Heightmap, NoiseGrid},
+    WorldSeed,
+};
+
+/// Height of the bedrock layer at the bottom of the world.
+pub const WORLD_BOTTOM_BORDER_HEIGHT: u32 = 2;
+
+/// Distance between two cave noise samples, the noise is interpolated in between.
+const CAVE_NOISE_STEP: u32 = 4;
+
+/// Parameters of the caves carved into the terrain of a biome.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default)]
+pub struct CaveParameters {
+    /// Frequency of the noise shaping the large open caves.
+    pub cheese_frequency: f64,
+    /// Noise value above which the large caves are carved, higher values make for fewer and smaller caves.
+    pub cheese_threshold: f64,
+    /// Large caves are kept at least this many voxels below the surface.
+    pub cheese_surface_depth: u32,
+    /// Frequency of the noise shaping the tunnels.
+    pub worm_frequency: f64,
+    /// Thickness of the tunnels in noise space, tunnels can break through the surface and form overhangs.
+    pub worm_radius: f64,
+}
+
+impl Default for CaveParameters {
+    fn default() -> Self {
+        Self {
+            cheese_frequency: 0.012,
+            cheese_threshold: 0.45,
+            cheese_surface_depth: 12,
+            worm_frequency: 0.008,
+            worm_radius: 0.07,
+        }
+    }
+}
+
+/// Generate the world bottom border for a chunk.
+pub fn terrain_generate_world_bottom_border(buffer: &mut VoxelBuffer<Voxel, ChunkShape>) {
+    buffer.fill_extent(
+        Extent::from_min_and_shape(
+            UVec3::ZERO,
+            UVec3::new(CHUNK_LENGTH, WORLD_BOTTOM_BORDER_HEIGHT, CHUNK_LENGTH),
+        ),
+        Bedrock::into_voxel(),
+    )
+}
+
+/// Carve the general terrain shape for a chunk.
+pub fn terrain_carve_heightmap(
+    buffer: &mut VoxelBuffer<Voxel, ChunkShape>,
+    key: IVec3,
+    heighmap: &Heightmap<CHUNK_LENGTH_U, CHUNK_LENGTH_U>,
+) {
+    // carve the terrain.
+    Extent::from_min_and_shape(UVec2::ZERO, UVec2::new(CHUNK_LENGTH, CHUNK_LENGTH))
+        .iter2()
+        .for_each(|pos| {
+            let local_height = heighmap
+                .get(pos.into())
+                .checked_sub(key.y as u32)
+                .unwrap_or_default()
+                .min(CHUNK_LENGTH);
+
+            for h in 0..local_height {
+                *buffer.voxel_at_mut([pos.x, h, pos.y].into()) = Rock::into_voxel();
+            }
+        });
+}
+
+/// Carve caves into the rock laid by [`terrain_carve_heightmap`] using 3D noise.
+/// Large "cheese" caves stay under the surface while "worm" tunnels, found where two noise fields are both close to zero, can open into it.
+/// The bedrock layer of the world bottom border is never carved.
+pub fn terrain_carve_caves(
+    buffer: &mut VoxelBuffer<Voxel, ChunkShape>,
+    key: IVec3,
+    heightmap: &Heightmap<CHUNK_LENGTH_U, CHUNK_LENGTH_U>,
+    seed: WorldSeed,
+    params: &CaveParameters,
+) {
+    let max_height = Extent::from_min_and_shape(UVec2::ZERO, UVec2::splat(CHUNK_LENGTH))
+        .iter2()
+        .map(|pos| heightmap.get(pos.into()))
+        .max()
+        .unwrap_or_default();
+
+    // nothing to carve in chunks above the surface.
+    if key.y as u32 >= max_height {
+        return;
+    }
+
+    let fbm = |seed: u32, frequency: f64| {
+        noise::Fbm::<noise::SuperSimplex>::new(seed)
+            .set_octaves(3)
+            .set_frequency(frequency)
+    };
+
+    let cheese = NoiseGrid::sample(
+        &fbm(seed.0, params.cheese_frequency),
+        key,
+        CHUNK_LENGTH,
+        CAVE_NOISE_STEP,
+    );
+    let worm_a = NoiseGrid::sample(
+        &fbm(seed.0.wrapping_add(1), params.worm_frequency),
+        key,
+        CHUNK_LENGTH,
+        CAVE_NOISE_STEP,
+    );
+    let worm_b = NoiseGrid::sample(
+        &fbm(seed.0.wrapping_add(2), params.worm_frequency),
+        key,
+        CHUNK_LENGTH,
+        CAVE_NOISE_STEP,
+    );
+
+    Extent::from_min_and_shape(UVec3::ZERO, UVec3::splat(CHUNK_LENGTH))
+        .iter3()
+        .for_each(|pos| {
+            let height = key.y as u32 + pos.y;
+            if height < WORLD_BOTTOM_BORDER_HEIGHT || buffer.voxel_at(pos) != Rock::into_voxel() {
+                return;
+            }
+
+            let surface = heightmap.get([pos.x, pos.z]);
+            let local = pos.to_array();
+
+            let in_cheese = height + params.cheese_surface_depth < surface
+                && cheese.get(local) > params.cheese_threshold;
+            let in_worm =
+                worm_a.get(local).powi(2) + worm_b.get(local).powi(2) < params.worm_radius.powi(2);
+
+            if in_cheese || in_worm {
+                *buffer.voxel_at_mut(pos) = Voxel::EMPTY_VOXEL;
+            }
+        });
+}
@@ -0,0 +1,420 @@
+use std::sync::RwLock;
+
+use bevy::{
+    asset::LoadState,
+    math::{IVec2, IVec3, Vec2, Vec3Swizzles},
+    prelude::{
+        warn, AddAsset, AssetEvent, AssetServer, Assets, EventReader, EventWriter, Handle, Plugin,
+        Res, ResMut, Resource,
+    },
+};
+use ilattice::{glam::UVec2, prelude::Extent};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use self::{
+    biomes::{
+        BiomeDefinition, BiomeDefinitionLoader, BiomeTerrainGenerator, IntoBoxedTerrainGenerator,
+    },
+    blending::{BiomeGrid, BiomeWeights},
+    climate::{ClimateNoise, ClimateRange},
+    common::{terrain_carve_caves, terrain_generate_world_bottom_border},
+    noise::{generate_heightmap_data, Heightmap, TERRAIN_BASE_HEIGHT},
+    structures::{STRUCTURE_MAX_DEPTH, STRUCTURE_MAX_REACH},
+    water::{terrain_flood, RiverNoise, BEACH_HEIGHT, DEFAULT_SEA_LEVEL},
+};
+use super::{storage::VoxelBuffer, ChunkShape, Voxel, CHUNK_LENGTH, CHUNK_LENGTH_U};
+
+/// the biomes defined by code and the loader for the biomes defined by asset files
+pub mod biomes;
+
+/// blending of the biomes near their borders
+pub mod blending;
+
+/// temperature and humidity driving the biome selection
+pub mod climate;
+
+/// noise functions ported over from C / GLSL code
+pub mod noise;
+
+/// common functions used by all terrain generators
+pub mod common;
+
+/// ore veins placement
+pub mod ores;
+
+/// structures spanning several chunks, such as trees
+pub mod structures;
+
+/// sea level flooding and river channels
+pub mod water;
+
+/// Structures are only placed on surfaces at or above this height.
+const STRUCTURE_MIN_HEIGHT: u32 = 128;
+
+/// Chunks at or above this height are left empty by the terrain generator.
+pub const TERRAIN_MAX_HEIGHT: i32 = 288;
+
+/// The seed all the terrain generation noise is derived from.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorldSeed(pub u32);
+
+impl WorldSeed {
+    /// Returns an offset to apply to the coordinates fed to the hash based noise functions,
+    /// so each seed samples a different part of the noise.
+    pub fn offset(&self) -> Vec2 {
+        Vec2::new((self.0 & 0xffff) as f32, (self.0 >> 16) as f32)
+    }
+}
+
+// Terrain generator singleton.
+pub static TERRAIN_GENERATOR: Lazy<RwLock<TerrainGenerator>> = Lazy::new(Default::default);
+
+pub struct TerrainGenerator {
+    /// The biomes along the climate they are found in, the biomes registered by code come first.
+    biomes: Vec<(ClimateRange, Box<dyn BiomeTerrainGenerator>)>,
+    /// Number of biomes registered by code, followed by the biomes loaded from [`BiomeDefinition`] assets.
+    registered_biomes: usize,
+    seed: WorldSeed,
+    /// Height below which the terrain is flooded.
+    sea_level: u32,
+}
+
+impl Default for TerrainGenerator {
+    fn default() -> Self {
+        Self {
+            biomes: Vec::new(),
+            registered_biomes: 0,
+            seed: WorldSeed::default(),
+            sea_level: DEFAULT_SEA_LEVEL,
+        }
+    }
+}
+
+impl TerrainGenerator {
+    pub fn set_seed(&mut self, seed: WorldSeed) -> &mut Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn seed(&self) -> WorldSeed {
+        self.seed
+    }
+
+    pub fn set_sea_level(&mut self, sea_level: u32) -> &mut Self {
+        self.sea_level = sea_level;
+        self
+    }
+
+    pub fn sea_level(&self) -> u32 {
+        self.sea_level
+    }
+
+    pub fn register_biome_generator(
+        &mut self,
+        climate: ClimateRange,
+        biome: Box<dyn BiomeTerrainGenerator>,
+    ) -> &mut Self {
+        self.biomes.insert(self.registered_biomes, (climate, biome));
+        self.registered_biomes += 1;
+        self
+    }
+
+    /// Replaces the biomes previously loaded from biome definition assets.
+    pub fn set_loaded_biomes<'a>(
+        &mut self,
+        definitions: impl Iterator<Item = &'a BiomeDefinition>,
+    ) -> &mut Self {
+        self.biomes.truncate(self.registered_biomes);
+        self.biomes.extend(definitions.map(|definition| {
+            (
+                definition.climate.clone(),
+                definition.clone().into_boxed_generator() as Box<dyn BiomeTerrainGenerator>,
+            )
+        }));
+        self
+    }
+
+    /// Returns the index of the biome with the closest temp / humidity to the climate of a world column.
+    /// When several biome climates overlap, the first registered biome wins.
+    fn biome_at(&self, climate_noise: &ClimateNoise, column: IVec2) -> usize {
+        let climate = climate_noise.climate_at(column);
+
+        self.biomes
+            .iter()
+            .enumerate()
+            .map(|(index, (range, _))| (index, range.distance(climate)))
+            .fold((0, f32::INFINITY), |closest, biome| {
+                if biome.1 < closest.1 {
+                    biome
+                } else {
+                    closest
+                }
+            })
+            .0
+    }
+
+    /// Computes the surface height of a column by blending the relief of the biomes surrounding it into its terrain height,
+    /// then carving the river valley it may lie in.
+    fn surface_height(
+        &self,
+        column: IVec2,
+        height: f32,
+        weights: &BiomeWeights,
+        rivers: &RiverNoise,
+    ) -> u32 {
+        let height = weights
+            .iter()
+            .map(|(biome, weight)| {
+                let relief = self.biomes[*biome].1.surface_relief();
+                weight * (height - TERRAIN_BASE_HEIGHT).mul_add(relief.scale, relief.offset)
+            })
+            .sum::<f32>()
+            + TERRAIN_BASE_HEIGHT;
+
+        rivers
+            .carve(column, height, self.sea_level)
+            .round()
+            .max(0.0) as u32
+    }
+
+    /// Picks the biome providing the surface of a column, dithering between the biomes surrounding it
+    /// so their strata mix near borders.
+    #[allow(clippy::borrowed_box)]
+    fn surface_biome(
+        &self,
+        column: IVec2,
+        weights: &BiomeWeights,
+    ) -> &Box<dyn BiomeTerrainGenerator> {
+        let mut threshold = noise::rand2to1(
+            column.as_vec2() * 0.1 + self.seed.offset(),
+            Vec2::new(39.346, 11.135),
+        )
+        .abs();
+
+        let biome = weights
+            .iter()
+            .find(|(_, weight)| {
+                threshold -= weight;
+                threshold < 0.0
+            })
+            .or(weights.last())
+            .map_or(0, |(biome, _)| *biome);
+
+        &self.biomes[biome].1
+    }
+
+    /// Places the voxels of the structures anchored in this chunk or close enough to overlap it.
+    fn place_structures(
+        &self,
+        chunk_key: IVec3,
+        biome_grid: &BiomeGrid,
+        buffer: &mut VoxelBuffer<Voxel, ChunkShape>,
+    ) {
+        let chunk_len = CHUNK_LENGTH as i32;
+
+        // the chunks lying below the lowest anchor of the structures, minus their depth, can't hold any of their voxels.
+        let lowest_anchor = STRUCTURE_MIN_HEIGHT.max(self.sea_level + BEACH_HEIGHT + 1) as i32;
+        if chunk_key.y + chunk_len + STRUCTURE_MAX_DEPTH <= lowest_anchor {
+            return;
+        }
+
+        let heightmap_noise = noise::heightmap_noise(self.seed);
+        let rivers = RiverNoise::new(self.seed);
+
+        let reach = STRUCTURE_MAX_REACH;
+        for z in chunk_key.z - reach..chunk_key.z + chunk_len + reach {
+            for x in chunk_key.x - reach..chunk_key.x + chunk_len + reach {
+                let column = IVec2::new(x, z);
+                let weights = biome_grid.weights(column);
+
+                let Some(structure) = self
+                    .surface_biome(column, &weights)
+                    .structure_at(column, self.seed)
+                else {
+                    continue;
+                };
+
+                let height = self.surface_height(
+                    column,
+                    noise::column_height(&heightmap_noise, column),
+                    &weights,
+                    &rivers,
+                );
+                if height >= STRUCTURE_MIN_HEIGHT && height > self.sea_level + BEACH_HEIGHT {
+                    structures::place_structure(
+                        buffer,
+                        chunk_key,
+                        IVec3::new(x, height as i32, z),
+                        structure.as_ref(),
+                    );
+                }
+            }
+        }
+    }
+
+    pub fn generate(&self, chunk_key: IVec3, buffer: &mut VoxelBuffer<Voxel, ChunkShape>) {
+        let chunk_len = CHUNK_LENGTH as i32;
+        let climate_noise = ClimateNoise::new(self.seed);
+        let biome_grid = BiomeGrid::new(chunk_key.xz(), chunk_len, STRUCTURE_MAX_REACH, |column| {
+            self.biome_at(&climate_noise, column)
+        });
+
+        let columns: Vec<(UVec2, BiomeWeights)> =
+            Extent::from_min_and_shape(UVec2::ZERO, UVec2::splat(CHUNK_LENGTH))
+                .iter2()
+                .map(|pos| (pos, biome_grid.weights(chunk_key.xz() + pos.as_ivec2())))
+                .collect();
+
+        let rivers = RiverNoise::new(self.seed);
+        let mut heights = generate_heightmap_data(chunk_key, CHUNK_LENGTH_U, self.seed);
+        for (pos, weights) in columns.iter() {
+            let column = chunk_key.xz() + pos.as_ivec2();
+            let height = &mut heights[pos.y as usize * CHUNK_LENGTH_U + pos.x as usize];
+            *height = self.surface_height(column, *height, weights, &rivers) as f32;
+        }
+
+        let noise_map = Heightmap::<CHUNK_LENGTH_U, CHUNK_LENGTH_U>::from_slice(&heights);
+
+        // caves and ores don't need to follow the biome borders closely.
+        let biome = &self.biomes[biome_grid.nearest_biome(chunk_key.xz() + chunk_len / 2)].1;
+
+        common::terrain_carve_heightmap(buffer, chunk_key, &noise_map);
+        terrain_carve_caves(
+            buffer,
+            chunk_key,
+            &noise_map,
+            self.seed,
+            &biome.cave_parameters(),
+        );
+
+        for (pos, weights) in columns.iter() {
+            self.surface_biome(chunk_key.xz() + pos.as_ivec2(), weights)
+                .carve_column(
+                    chunk_key,
+                    *pos,
+                    noise_map.get((*pos).into()),
+                    self.sea_level,
+                    buffer,
+                );
+        }
+
+        terrain_flood(buffer, chunk_key, &noise_map, self.sea_level);
+
+        ores::terrain_place_ores(buffer, chunk_key, self.seed, biome.ores());
+        self.place_structures(chunk_key, &biome_grid, buffer);
+
+        if chunk_key.y == 0 {
+            terrain_generate_world_bottom_border(buffer);
+        }
+    }
+}
+
+/// Directory of the asset folder holding the `.biome.ron` biome definitions.
+const BIOME_DEFINITIONS_DIR: &str = "biomes";
+
+/// The biome definition assets loaded at startup.
+#[derive(Resource, Default)]
+pub struct BiomeDefinitions {
+    handles: Vec<Handle<BiomeDefinition>>,
+    loaded: bool,
+}
+
+/// Sent when the biome definitions are modified after they were first loaded,
+/// the chunks generated before no longer match the terrain generator.
+pub struct BiomeDefinitionsReloaded;
+
+fn load_biome_definitions(
+    asset_server: Res<AssetServer>,
+    mut definitions: ResMut<BiomeDefinitions>,
+) {
+    match asset_server.load_folder(BIOME_DEFINITIONS_DIR) {
+        Ok(handles) => {
+            definitions.handles = handles.into_iter().map(|handle| handle.typed()).collect();
+        }
+        Err(err) => warn!("cannot load the biome definitions: {err}"),
+    }
+}
+
+/// Hands the biome definitions over to the terrain generator once they are all loaded, then again each time one of them changes.
+/// Definitions are registered by name so every client and the server order them the same way.
+fn apply_biome_definitions(
+    mut events: EventReader<AssetEvent<BiomeDefinition>>,
+    mut reloaded: EventWriter<BiomeDefinitionsReloaded>,
+    mut definitions: ResMut<BiomeDefinitions>,
+    assets: Res<Assets<BiomeDefinition>>,
+    asset_server: Res<AssetServer>,
+) {
+    let changed = events.iter().count() > 0;
+    if definitions.loaded && !changed {
+        return;
+    }
+
+    let load_state =
+        asset_server.get_group_load_state(definitions.handles.iter().map(|handle| handle.id()));
+    match load_state {
+        LoadState::Loaded | LoadState::Failed => {}
+        _ => return,
+    }
+
+    let mut loaded: Vec<&BiomeDefinition> = definitions
+        .handles
+        .iter()
+        .filter_map(|handle| assets.get(handle))
+        .collect();
+    loaded.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+
+    TERRAIN_GENERATOR
+        .write()
+        .unwrap()
+        .set_loaded_biomes(loaded.into_iter());
+
+    if definitions.loaded {
+        reloaded.send(BiomeDefinitionsReloaded);
+    }
+    definitions.loaded = true;
+}
+
+/// Run condition holding once the biome definitions are handed over to the terrain generator,
+/// no terrain should be generated before.
+pub fn biome_definitions_loaded(definitions: Res<BiomeDefinitions>) -> bool {
+    definitions.loaded
+}
+
+pub struct TerrainGeneratorPlugin;
+
+impl Plugin for TerrainGeneratorPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        TERRAIN_GENERATOR
+            .write()
+            .unwrap()
+            .register_biome_generator(
+                ClimateRange {
+                    temperature: -0.35..0.35,
+                    humidity: -1.0..0.3,
+                },
+                biomes::BasicPlainsBiomeTerrainGenerator.into_boxed_generator(),
+            )
+            .register_biome_generator(
+                ClimateRange {
+                    temperature: 0.35..1.0,
+                    humidity: -1.0..0.2,
+                },
+                biomes::BasicDesertBiomeTerrainGenerator.into_boxed_generator(),
+            )
+            .register_biome_generator(
+                ClimateRange {
+                    temperature: -1.0..-0.35,
+                    humidity: -1.0..1.0,
+                },
+                biomes::BasicSnowyPlainsBiomeTerrainGenerator.into_boxed_generator(),
+            );
+
+        app.add_asset::<BiomeDefinition>()
+            .init_asset_loader::<BiomeDefinitionLoader>()
+            .init_resource::<BiomeDefinitions>()
+            .add_event::<BiomeDefinitionsReloaded>()
+            .add_startup_system(load_biome_definitions)
+            .add_system(apply_biome_definitions);
+    }
+}
@@ -0,0 +1,195 @@
+use bevy::math::{IVec2, IVec3, Vec2, Vec2Swizzles, Vec3, Vec3Swizzles};
+use noise::{utils::NoiseMapBuilder, MultiFractal, NoiseFn};
+
+use super::WorldSeed;
+
+pub fn rand2to1(p: Vec2, dot: Vec2) -> f32 {
+    let sp: Vec2 = p.to_array().map(|x| x.sin()).into();
+    let random = sp.dot(dot);
+    (random.sin() * 143_758.55).fract()
+}
+
+#[inline(always)]
+pub fn rand2to2(p: Vec2) -> Vec2 {
+    Vec2::new(
+        rand2to1(p, Vec2::new(12.989, 78.233)),
+        rand2to1(p, Vec2::new(39.346, 11.135)),
+    )
+}
+
+pub fn rand2to1i(vec: Vec2) -> f32 {
+    let mut p3 = (vec.xyx() * 0.39).fract();
+    p3 += p3.dot(p3.yzx());
+    (p3.x + p3.y) * p3.z.fract()
+}
+
+#[allow(dead_code)]
+#[inline(always)]
+pub fn rand2to3(p: Vec2) -> Vec3 {
+    Vec3::new(
+        rand2to1(p, Vec2::new(12.989, 78.233)),
+        rand2to1(p, Vec2::new(39.346, 11.135)),
+        rand2to1(p, Vec2::new(73.156, 52.235)),
+    )
+}
+
+#[allow(dead_code)]
+#[inline(always)]
+pub fn rand1dto1d(p: f32, mutator: f32) -> f32 {
+    let random = (p + mutator).sin();
+    (random * 143_758.55).fract()
+}
+
+#[allow(dead_code)]
+#[inline(always)]
+pub fn rand1to3(p: f32) -> Vec3 {
+    Vec3::new(
+        rand1dto1d(p, 3.9812),
+        rand1dto1d(p, 1.2345),
+        rand1dto1d(p, 5.4321),
+    )
+}
+
+// This was ported from the code at https://www.ronja-tutorials.com/post/028-voronoi-noise/
+pub fn voronoi(p: Vec2) -> Vec2 {
+    const NEIGHBOUR_RANGE: i32 = 2; // A neighbour range of 1 will generate some weird artifacts lets use 2.
+
+    let base_cell = p.floor();
+    let mut closest_point = base_cell;
+    let mut min_distance = 1f32;
+
+    for x in -NEIGHBOUR_RANGE..=NEIGHBOUR_RANGE {
+        for y in -NEIGHBOUR_RANGE..=NEIGHBOUR_RANGE {
+            let cell = base_cell + Vec2::new(x as f32, y as f32);
+            let cell_pos = cell + rand2to2(cell);
+            let distance = (cell_pos - p).length_squared(); // using non squarred length to increase the throughput (a bit)
+
+            if distance < min_distance {
+                min_distance = distance;
+                closest_point = cell;
+            }
+        }
+    }
+
+    closest_point
+}
+
+/// The noise shaping the terrain surface, sampled at world (x, z) coordinates.
+pub fn heightmap_noise(seed: WorldSeed) -> noise::Fbm<noise::SuperSimplex> {
+    noise::Fbm::<noise::SuperSimplex>::new(seed.0)
+        .set_octaves(4)
+        .set_frequency(0.005)
+        .set_persistence(0.5)
+        .set_lacunarity(2.0)
+}
+
+/// Height around which the terrain surface varies.
+pub const TERRAIN_BASE_HEIGHT: f32 = 132.0;
+
+/// Height the terrain surface varies by around its base height, before the biome relief is applied.
+pub const TERRAIN_HEIGHT_AMPLITUDE: f32 = 20.0;
+
+/// Maps a [`heightmap_noise`] value to a terrain height.
+#[inline]
+fn noise_to_height(value: f64) -> f32 {
+    value.mul_add(TERRAIN_HEIGHT_AMPLITUDE as f64, TERRAIN_BASE_HEIGHT as f64) as f32
+}
+
+/// Returns the terrain height of a single world column before biome relief is applied, matching [`generate_heightmap_data`].
+pub fn column_height(noise: &impl NoiseFn<f64, 2>, column: IVec2) -> f32 {
+    noise_to_height(noise.get(column.as_dvec2().to_array()))
+}
+
+pub fn generate_heightmap_data(key: IVec3, chunk_len: usize, seed: WorldSeed) -> Vec<f32> {
+    noise::utils::PlaneMapBuilder::<_, 2>::new(heightmap_noise(seed))
+        .set_size(chunk_len, chunk_len)
+        .set_x_bounds(key.x as f64, (key.x + chunk_len as i32) as f64)
+        .set_y_bounds(key.z as f64, (key.z + chunk_len as i32) as f64)
+        .build()
+        .into_iter()
+        .map(noise_to_height)
+        .collect()
+}
+
+/// 3D noise sampled every `step` voxels over a chunk and trilinearly interpolated in between,
+/// which is much cheaper than sampling every voxel for low frequency noise.
+pub struct NoiseGrid {
+    values: Vec<f64>,
+    step: u32,
+    samples: u32,
+}
+
+impl NoiseGrid {
+    /// Samples the noise over the chunk at `key`, `step` must divide `chunk_len`.
+    pub fn sample(noise: &impl NoiseFn<f64, 3>, key: IVec3, chunk_len: u32, step: u32) -> Self {
+        let samples = chunk_len / step + 1;
+        let mut values = Vec::with_capacity(samples.pow(3) as usize);
+
+        for z in 0..samples {
+            for y in 0..samples {
+                for x in 0..samples {
+                    let pos = key + (IVec3::new(x as i32, y as i32, z as i32) * step as i32);
+                    values.push(noise.get(pos.as_dvec3().to_array()));
+                }
+            }
+        }
+
+        Self {
+            values,
+            step,
+            samples,
+        }
+    }
+
+    #[inline]
+    fn sample_at(&self, x: u32, y: u32, z: u32) -> f64 {
+        self.values[((z * self.samples + y) * self.samples + x) as usize]
+    }
+
+    /// Gets the interpolated noise value at the specified local coordinates.
+    pub fn get(&self, pos: [u32; 3]) -> f64 {
+        let [x, y, z] = pos.map(|c| c / self.step);
+        let [fx, fy, fz] = pos.map(|c| (c % self.step) as f64 / self.step as f64);
+        let lerp = |a: f64, b: f64, t: f64| (b - a).mul_add(t, a);
+
+        let x00 = lerp(self.sample_at(x, y, z), self.sample_at(x + 1, y, z), fx);
+        let x10 = lerp(
+            self.sample_at(x, y + 1, z),
+            self.sample_at(x + 1, y + 1, z),
+            fx,
+        );
+        let x01 = lerp(
+            self.sample_at(x, y, z + 1),
+            self.sample_at(x + 1, y, z + 1),
+            fx,
+        );
+        let x11 = lerp(
+            self.sample_at(x, y + 1, z + 1),
+            self.sample_at(x + 1, y + 1, z + 1),
+            fx,
+        );
+
+        lerp(lerp(x00, x10, fy), lerp(x01, x11, fy), fz)
+    }
+}
+
+/// A view into a slice of noise values with W x H dimensions.
+/// Provides methods for fetching a value at specified coordinates and to map values to a range.
+#[derive(Clone, Copy)]
+pub struct Heightmap<'a, const W: usize, const H: usize> {
+    slice: &'a [f32],
+}
+
+impl<'a, const W: usize, const H: usize> Heightmap<'a, W, H> {
+    /// Gets the value at the specified coordinates.
+    #[inline]
+    pub fn get(&self, pos: [u32; 2]) -> u32 {
+        self.slice[pos[1] as usize * W + pos[0] as usize].round() as u32
+    }
+
+    /// Constructs a view into a slice of noise values with W x H dimensions.
+    #[inline]
+    pub const fn from_slice(slice: &'a [f32]) -> Self {
+        Self { slice }
+    }
+}
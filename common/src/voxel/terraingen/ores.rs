@@ -0,0 +1,106 @@
+use bevy::math::IVec3;
+use ilattice::glam::UVec3;
+
+use crate::voxel::{
+    material::VoxelMaterial,
+    materials::{CoalOre, DiamondOre, GoldOre, IronOre, Rock},
+    storage::VoxelBuffer,
+    ChunkShape, Voxel, CHUNK_LENGTH,
+};
+
+use super::WorldSeed;
+
+/// Describes how an ore is scattered through the rock.
+#[derive(Clone, Copy, Debug)]
+pub struct OreDistribution {
+    pub ore: Voxel,
+    /// Lowest world height at which a vein can start.
+    pub min_height: i32,
+    /// Highest world height at which a vein can start.
+    pub max_height: i32,
+    /// Number of voxels visited by a vein, some of them may not be rock and be left untouched.
+    pub vein_size: u32,
+    /// Average number of veins in a chunk lying within the height band.
+    pub veins_per_chunk: f32,
+}
+
+/// The ores found under most biomes.
+pub const DEFAULT_ORES: [OreDistribution; 4] = [
+    OreDistribution {
+        ore: Voxel::new(CoalOre::ID),
+        min_height: 16,
+        max_height: 140,
+        vein_size: 14,
+        veins_per_chunk: 6.0,
+    },
+    OreDistribution {
+        ore: Voxel::new(IronOre::ID),
+        min_height: 8,
+        max_height: 100,
+        vein_size: 8,
+        veins_per_chunk: 4.0,
+    },
+    OreDistribution {
+        ore: Voxel::new(GoldOre::ID),
+        min_height: 2,
+        max_height: 48,
+        vein_size: 6,
+        veins_per_chunk: 1.0,
+    },
+    OreDistribution {
+        ore: Voxel::new(DiamondOre::ID),
+        min_height: 2,
+        max_height: 20,
+        vein_size: 4,
+        veins_per_chunk: 0.4,
+    },
+];
+
+/// Hashes the world seed, chunk key and ore index into a seed for the vein placement rng, so a chunk always gets the same veins.
+fn vein_seed(seed: WorldSeed, key: IVec3, ore_index: usize) -> u64 {
+    let mut hash = seed.0 as u64;
+    for value in [key.x, key.y, key.z, ore_index as i32] {
+        hash = (hash ^ value as u32 as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        hash ^= hash >> 31;
+    }
+    hash
+}
+
+/// Places ore veins in the rock of a chunk as random walks starting within each ore height band.
+pub fn terrain_place_ores(
+    buffer: &mut VoxelBuffer<Voxel, ChunkShape>,
+    key: IVec3,
+    seed: WorldSeed,
+    ores: &[OreDistribution],
+) {
+    for (index, ore) in ores.iter().enumerate() {
+        let min_y = (ore.min_height - key.y).max(0);
+        let max_y = (ore.max_height - key.y).min(CHUNK_LENGTH as i32 - 1);
+        if min_y > max_y {
+            continue;
+        }
+
+        let rng = fastrand::Rng::with_seed(vein_seed(seed, key, index));
+        let veins =
+            ore.veins_per_chunk.trunc() as u32 + (rng.f32() < ore.veins_per_chunk.fract()) as u32;
+
+        for _ in 0..veins {
+            let mut pos = IVec3::new(
+                rng.i32(0..CHUNK_LENGTH as i32),
+                rng.i32(min_y..=max_y),
+                rng.i32(0..CHUNK_LENGTH as i32),
+            );
+
+            for _ in 0..ore.vein_size {
+                let local = UVec3::from(pos.as_uvec3().to_array());
+                if buffer.voxel_at(local) == Rock::into_voxel() {
+                    *buffer.voxel_at_mut(local) = ore.ore;
+                }
+
+                let mut step = IVec3::ZERO;
+                step[rng.usize(0..3)] = if rng.bool() { 1 } else { -1 };
+                pos = (pos + step).clamp(IVec3::ZERO, IVec3::splat(CHUNK_LENGTH as i32 - 1));
+            }
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use bevy::math::{IVec3, Vec3};
+
+use crate::voxel::{sdf, storage::VoxelBuffer, ChunkShape, Voxel, CHUNK_LENGTH};
+
+/// Maximum horizontal distance between the anchor of a structure and its furthest voxel.
+/// Chunks look for the structures anchored up to this distance away from their borders.
+pub const STRUCTURE_MAX_REACH: i32 = 8;
+
+/// Maximum depth of the voxels of a structure below its anchor.
+/// Chunks lying deeper below the lowest surface structures can hold do not look for structures at all.
+pub const STRUCTURE_MAX_DEPTH: i32 = 8;
+
+/// A terrain feature (tree, cactus, building...) anchored at a world position, which may span several chunks.
+pub trait Structure: Send + Sync {
+    /// Returns the minimum (inclusive) and maximum (exclusive) offsets of the structure voxels from its anchor.
+    fn bounds(&self) -> (IVec3, IVec3);
+
+    /// Returns the voxel of the structure at the specified offset from its anchor, if any.
+    fn voxel_at(&self, offset: Vec3) -> Option<Voxel>;
+}
+
+/// Writes the voxels of a structure anchored at `anchor` which lie within the chunk at `chunk_key`.
+/// Every chunk overlapped by the structure places its own part, so the result doesn't depend on the chunk generation order.
+pub fn place_structure(
+    buffer: &mut VoxelBuffer<Voxel, ChunkShape>,
+    chunk_key: IVec3,
+    anchor: IVec3,
+    structure: &dyn Structure,
+) {
+    let (min, max) = structure.bounds();
+    let min = (anchor + min).max(chunk_key);
+    let max = (anchor + max).min(chunk_key + IVec3::splat(CHUNK_LENGTH as i32));
+
+    if min.cmpge(max).any() {
+        return;
+    }
+
+    for z in min.z..max.z {
+        for y in min.y..max.y {
+            for x in min.x..max.x {
+                let pos = IVec3::new(x, y, z);
+
+                if let Some(voxel) = structure.voxel_at((pos - anchor).as_vec3()) {
+                    *buffer.voxel_at_mut((pos - chunk_key).as_uvec3().to_array().into()) = voxel;
+                }
+            }
+        }
+    }
+}
+
+/// A tree with a round crown, made using SDF functions.
+pub struct Tree {
+    pub trunk: Voxel,
+    pub leaves: Voxel,
+}
+
+impl Structure for Tree {
+    fn bounds(&self) -> (IVec3, IVec3) {
+        (IVec3::new(-6, -6, -6), IVec3::new(7, 21, 7))
+    }
+
+    fn voxel_at(&self, offset: Vec3) -> Option<Voxel> {
+        if sdf::sdf_sphere(offset - 14.0 * Vec3::Y, 6.0) < 0. {
+            Some(self.leaves)
+        } else if sdf::sdf_capped_cylinder(offset - 2.0 * Vec3::Y, 1.5, 8.0) < 0. {
+            Some(self.trunk)
+        } else {
+            None
+        }
+    }
+}
+
+/// A tree with a conical crown, made using SDF functions.
+pub struct PineTree {
+    pub trunk: Voxel,
+    pub leaves: Voxel,
+}
+
+impl Structure for PineTree {
+    fn bounds(&self) -> (IVec3, IVec3) {
+        (IVec3::new(-7, -6, -7), IVec3::new(8, 24, 8))
+    }
+
+    fn voxel_at(&self, offset: Vec3) -> Option<Voxel> {
+        if sdf::sdf_vcone(offset - 6.0 * Vec3::Y, 7.0, 17.0) < 0. {
+            Some(self.leaves)
+        } else if sdf::sdf_capped_cylinder(offset - 2.0 * Vec3::Y, 1.5, 8.0) < 0. {
+            Some(self.trunk)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use bevy::{
+    math::{IVec2, IVec3},
+    prelude::Resource,
+};
+use ilattice::{glam::UVec2, prelude::Extent};
+use noise::{Fbm, MultiFractal, NoiseFn, SuperSimplex};
+use serde::{Deserialize, Serialize};
+
+use crate::voxel::{
+    material::VoxelMaterial, materials::Water, storage::VoxelBuffer, ChunkShape, Voxel,
+    CHUNK_LENGTH, CHUNK_LENGTH_U,
+};
+
+use super::{noise::Heightmap, WorldSeed};
+
+/// Default height of the sea surface, the terrain below it is flooded.
+pub const DEFAULT_SEA_LEVEL: u32 = 122;
+
+/// Height of the sea surface of a world, chosen by the server and sent to its clients along the world seed.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SeaLevel(pub u32);
+
+impl Default for SeaLevel {
+    fn default() -> Self {
+        Self(DEFAULT_SEA_LEVEL)
+    }
+}
+
+/// Columns whose surface lies at most this many voxels above the sea level are covered with beach strata.
+pub const BEACH_HEIGHT: u32 = 2;
+
+/// Half width of the river channels, in river noise units.
+const RIVER_WIDTH: f64 = 0.012;
+
+/// Half width of the valleys sloping down to the rivers, in river noise units.
+const RIVER_VALLEY_WIDTH: f64 = 0.06;
+
+/// Depth of the river beds below the sea level.
+const RIVER_DEPTH: f32 = 3.0;
+
+/// The noise shaping the river network, rivers flow along its zero crossings.
+pub struct RiverNoise(Fbm<SuperSimplex>);
+
+impl RiverNoise {
+    pub fn new(seed: WorldSeed) -> Self {
+        Self(
+            Fbm::<SuperSimplex>::new(seed.0.wrapping_add(5))
+                .set_octaves(3)
+                .set_frequency(0.0015),
+        )
+    }
+
+    /// Lowers the height of a world column lying in a river valley.
+    /// River beds sit below the sea level so the channels are flooded along with the seas they connect to,
+    /// and the noise being sampled in world space, the channels continue across chunk borders.
+    pub fn carve(&self, column: IVec2, height: f32, sea_level: u32) -> f32 {
+        let distance = self.0.get(column.as_dvec2().to_array()).abs();
+        let bed = sea_level as f32 - RIVER_DEPTH;
+
+        if distance >= RIVER_VALLEY_WIDTH || height <= bed {
+            return height;
+        }
+
+        let t = ((distance - RIVER_WIDTH) / (RIVER_VALLEY_WIDTH - RIVER_WIDTH)).max(0.0) as f32;
+        let slope = t * t * 2.0f32.mul_add(-t, 3.0);
+        (height - bed).mul_add(slope, bed)
+    }
+}
+
+/// Floods the empty voxels between the surface of each column and the sea level, forming the seas, lakes and rivers.
+/// Caves lying under the surface are left dry.
+pub fn terrain_flood(
+    buffer: &mut VoxelBuffer<Voxel, ChunkShape>,
+    key: IVec3,
+    heightmap: &Heightmap<CHUNK_LENGTH_U, CHUNK_LENGTH_U>,
+    sea_level: u32,
+) {
+    let chunk_min = key.y as u32;
+    if chunk_min > sea_level {
+        return;
+    }
+
+    let max = (sea_level - chunk_min).min(CHUNK_LENGTH - 1);
+
+    Extent::from_min_and_shape(UVec2::ZERO, UVec2::splat(CHUNK_LENGTH))
+        .iter2()
+        .for_each(|pos| {
+            let min = heightmap
+                .get(pos.into())
+                .saturating_sub(chunk_min)
+                .min(CHUNK_LENGTH);
+
+            for h in min..=max {
+                let voxel = buffer.voxel_at_mut([pos.x, h, pos.y].into());
+                if *voxel == Voxel::EMPTY_VOXEL {
+                    *voxel = Water::into_voxel();
+                }
+            }
+        });
+}
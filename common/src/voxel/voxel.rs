@@ -0,0 +1,68 @@
+use block_mesh::{MergeVoxel, Voxel as MeshableVoxel};
+use serde::{Deserialize, Serialize};
+
+/// A voxel made of the id of its material and a byte of material specific state,
+/// such as an orientation, a liquid level or a growth stage.
+#[derive(Copy, Clone, Hash, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Voxel {
+    pub id: u16,
+    pub state: u8,
+}
+
+impl Voxel {
+    pub const EMPTY_VOXEL: Self = Self::new(0);
+
+    /// Returns a voxel of the specified material with a zeroed state.
+    #[inline]
+    pub const fn new(id: u16) -> Self {
+        Self { id, state: 0 }
+    }
+
+    /// Returns this voxel with its state replaced.
+    #[inline]
+    pub const fn with_state(self, state: u8) -> Self {
+        Self { state, ..self }
+    }
+
+    #[inline]
+    pub const fn is_empty(&self) -> bool {
+        self.id == Self::EMPTY_VOXEL.id
+    }
+}
+
+impl MeshableVoxel for Voxel {
+    #[inline]
+    fn get_visibility(&self) -> block_mesh::VoxelVisibility {
+        if self.is_empty() {
+            block_mesh::VoxelVisibility::Empty
+        } else {
+            block_mesh::VoxelVisibility::Opaque
+        }
+    }
+}
+
+impl MergeVoxel for Voxel {
+    type MergeValue = Self;
+
+    // voxels in different states may render differently.
+    #[inline]
+    fn merge_value(&self) -> Self::MergeValue {
+        *self
+    }
+}
+
+pub trait MaterialVoxel: MergeVoxel + MeshableVoxel {
+    fn as_mat_id(&self) -> u16;
+
+    fn state(&self) -> u8;
+}
+
+impl MaterialVoxel for Voxel {
+    fn as_mat_id(&self) -> u16 {
+        self.id
+    }
+
+    fn state(&self) -> u8 {
+        self.state
+    }
+}
@@ -10,10 +10,34 @@ use bevy_renet::{
 };
 
 use common::{
-    connection_config, ClientChannel, NetworkedEntities, Player, PlayerInput, RotationInput,
-    ServerChannel, ServerMessages, PROTOCOL_ID,
+    connection_config,
+    time_of_day::TimeOfDay,
+    voxel::{
+        fluids::{FluidSimulation, FLUID_TICK_INTERVAL},
+        material::{
+            material_ids_with_flags, VoxelMaterial, VoxelMaterialFlags, MATERIAL_DEFINITIONS_PATH,
+        },
+        materials::Bedrock,
+        storage::ChunkMap,
+        terraingen::{
+            biome_definitions_loaded, common::WORLD_BOTTOM_BORDER_HEIGHT, water::SeaLevel,
+            WorldSeed,
+        },
+        ChunkShape, Voxel,
+    },
+    ChunkEditsRequest, ClientChannel, NetworkedEntities, Player, PlayerInput, RotationInput,
+    ServerChannel, ServerMessages, VoxelEdit, VoxelEditBatch, VoxelFall, VoxelFallBatch,
+    CHUNK_LENGTH, PROTOCOL_ID,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    f32::consts::PI,
+    net::UdpSocket,
+    path::Path,
+    time::SystemTime,
 };
-use std::{collections::HashMap, f32::consts::PI, net::UdpSocket, time::SystemTime};
+
+mod world;
 
 #[derive(Debug, Default, Resource)]
 pub struct ServerLobby {
@@ -23,6 +47,64 @@ pub struct ServerLobby {
 #[derive(Debug, Resource)]
 struct BotId(u64);
 
+/// Seconds between two broadcasts of the time of day, which the clients advance on their own in between.
+const TIME_OF_DAY_SYNC_INTERVAL: f32 = 5.0;
+
+/// Maximum distance between a player and the voxels it is allowed to edit.
+const MAX_VOXEL_EDIT_DISTANCE: f32 = 16.0;
+
+/// Maximum distance between a player and the voxels it saw falling from, as columns collapse above the voxels it edits
+/// and the player moves while they fall. The server world keeps the chunks in this reach loaded to check the falls.
+pub const MAX_VOXEL_FALL_REACH: f32 = MAX_VOXEL_EDIT_DISTANCE + 16.0;
+
+/// Every accepted voxel edit not saved to disk yet, grouped by chunk so they can be sent to clients loading a chunk.
+/// The edits of a chunk are dropped once the server saves the chunk, which then holds them.
+#[derive(Debug, Default, Resource)]
+struct VoxelEditLog {
+    chunks: HashMap<IVec3, HashMap<IVec3, Voxel>>,
+    /// Chunks saved along their edits, which clients generating their terrain receive in full instead.
+    saved: HashSet<IVec3>,
+}
+
+impl VoxelEditLog {
+    fn record(&mut self, edit: VoxelEdit) {
+        let chunk_min = !IVec3::splat(CHUNK_LENGTH as i32 - 1) & edit.position;
+        self.chunks
+            .entry(chunk_min)
+            .or_default()
+            .insert(edit.position, edit.voxel);
+    }
+
+    /// Returns the voxel last written at the specified position, if it was ever edited.
+    fn voxel_at(&self, position: IVec3) -> Option<Voxel> {
+        let chunk_min = !IVec3::splat(CHUNK_LENGTH as i32 - 1) & position;
+        self.chunks.get(&chunk_min)?.get(&position).copied()
+    }
+
+    /// Drops the edits of a chunk which was just saved to disk.
+    fn forget_saved_chunk(&mut self, chunk_min: IVec3) {
+        if self.chunks.remove(&chunk_min).is_some() {
+            self.saved.insert(chunk_min);
+        }
+    }
+
+    /// Whether edits of the chunk were dropped from the log once the chunk was saved.
+    fn is_saved(&self, chunk_min: IVec3) -> bool {
+        self.saved.contains(&chunk_min)
+    }
+
+    fn chunk_edits(&self, chunk_min: IVec3) -> impl Iterator<Item = VoxelEdit> + '_ {
+        self.chunks
+            .get(&chunk_min)
+            .into_iter()
+            .flatten()
+            .map(|(position, voxel)| VoxelEdit {
+                position: *position,
+                voxel: *voxel,
+            })
+    }
+}
+
 fn new_renet_server() -> (RenetServer, NetcodeServerTransport) {
     let server = RenetServer::new(connection_config());
 
@@ -43,23 +125,91 @@ fn new_renet_server() -> (RenetServer, NetcodeServerTransport) {
     (server, transport)
 }
 
+/// Returns the value following the specified command line argument, if any.
+fn arg_value(name: &str) -> Option<String> {
+    let mut args = std::env::args();
+    args.find(|arg| arg == name)?;
+    args.next()
+}
+
+/// Parses the value of the specified command line argument, exiting with a message describing the expected value if it is invalid.
+fn parsed_arg_value<T: std::str::FromStr>(name: &str, expected: &str) -> Option<T> {
+    let value = arg_value(name)?;
+    match value.parse() {
+        Ok(parsed) => Some(parsed),
+        Err(_) => {
+            eprintln!("invalid value `{value}` for {name}: expected {expected}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Reads the world seed from the `--seed <seed>` command line argument.
+fn world_seed_from_args() -> WorldSeed {
+    parsed_arg_value("--seed", "an unsigned integer").map_or_else(WorldSeed::default, WorldSeed)
+}
+
+/// Reads the height of the sea surface from the `--sea-level <height>` command line argument.
+fn sea_level_from_args() -> SeaLevel {
+    parsed_arg_value("--sea-level", "an unsigned integer").map_or_else(SeaLevel::default, SeaLevel)
+}
+
+/// Reads the length of a day in seconds from the `--day-length <seconds>` command line argument.
+fn time_of_day_from_args() -> TimeOfDay {
+    parsed_arg_value("--day-length", "a number of seconds").map_or_else(
+        TimeOfDay::default,
+        |day_length| TimeOfDay {
+            day_length,
+            ..default()
+        },
+    )
+}
+
+/// Reads the asset folder holding the biome definitions from the `--assets <dir>` command line argument,
+/// the server sharing the client assets by default.
+fn asset_folder_from_args() -> String {
+    arg_value("--assets")
+        .unwrap_or_else(|| concat!(env!("CARGO_MANIFEST_DIR"), "/../client/assets").into())
+}
+
 fn main() {
     let mut app = App::new();
     let (server, transport) = new_renet_server();
-    app.add_plugin(AssetPlugin::default())
-        .add_asset::<Mesh>()
-        .add_asset::<Scene>()
-        .insert_resource(SceneSpawner::default())
-        .add_plugins(MinimalPlugins)
-        .add_plugin(RenetServerPlugin)
-        .add_plugin(NetcodeServerPlugin)
-        .add_plugin(RapierPhysicsPlugin::<NoUserData>::default())
-        .insert_resource(ServerLobby::default())
-        .insert_resource(BotId(0))
-        .insert_resource(server)
-        .insert_resource(transport)
-        .add_systems((server_update_system, server_network_sync))
-        .run();
+    app.add_plugin(AssetPlugin {
+        asset_folder: asset_folder_from_args(),
+        ..default()
+    })
+    .add_asset::<Mesh>()
+    .add_asset::<Scene>()
+    .insert_resource(SceneSpawner::default())
+    .insert_resource(world_seed_from_args())
+    .insert_resource(sea_level_from_args())
+    .add_plugins(MinimalPlugins)
+    .add_plugin(RenetServerPlugin)
+    .add_plugin(NetcodeServerPlugin)
+    .add_plugin(RapierPhysicsPlugin::<NoUserData>::default())
+    .add_plugin(world::ServerWorldPlugin)
+    .insert_resource(ServerLobby::default())
+    .insert_resource(BotId(0))
+    .init_resource::<VoxelEditLog>()
+    .init_resource::<FluidSimulation>()
+    .init_resource::<UnsettledFluids>()
+    .init_resource::<GravityMaterials>()
+    .add_startup_system(setup_material_flags)
+    .insert_resource(time_of_day_from_args())
+    .insert_resource(server)
+    .insert_resource(transport)
+    .add_systems((
+        server_update_system,
+        server_network_sync,
+        server_voxel_edits_system,
+        server_voxel_falls_system.after(server_voxel_edits_system),
+        server_fluids_system
+            .after(server_voxel_falls_system)
+            .run_if(biome_definitions_loaded),
+        server_time_of_day_system,
+    ))
+    .run();
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -69,12 +219,28 @@ fn server_update_system(
     mut lobby: ResMut<ServerLobby>,
     mut server: ResMut<RenetServer>,
     mut players: Query<(Entity, &Player, &mut Transform)>,
+    world_seed: Res<WorldSeed>,
+    sea_level: Res<SeaLevel>,
+    time_of_day: Res<TimeOfDay>,
 ) {
     for event in server_events.iter() {
         //TODO: ADAPT
         match event {
             ServerEvent::ClientConnected { client_id } => {
                 println!("Player {} connected.", client_id);
+                let message = bincode::serialize(&ServerMessages::SeaLevel {
+                    sea_level: *sea_level,
+                })
+                .unwrap();
+                server.send_message(*client_id, ServerChannel::ServerMessages, message);
+                let message =
+                    bincode::serialize(&ServerMessages::WorldSeed { seed: *world_seed }).unwrap();
+                server.send_message(*client_id, ServerChannel::ServerMessages, message);
+                let message = bincode::serialize(&ServerMessages::TimeOfDay {
+                    time_of_day: *time_of_day,
+                })
+                .unwrap();
+                server.send_message(*client_id, ServerChannel::ServerMessages, message);
                 if lobby.players.is_empty() {
                     let host = true;
                     let message = bincode::serialize(&host).unwrap();
@@ -159,6 +325,356 @@ fn server_update_system(
     }
 }
 
+/// Returns the voxel the server holds at the specified position, if its chunk is loaded or the voxel was edited since.
+/// Chunks are never generated here, as clients may send edits anywhere in the world.
+fn authoritative_voxel(
+    chunks: &ChunkMap<Voxel, ChunkShape>,
+    edit_log: &VoxelEditLog,
+    position: IVec3,
+) -> Option<Voxel> {
+    chunks
+        .voxel_at(position)
+        .or_else(|| edit_log.voxel_at(position))
+}
+
+/// Applies an accepted voxel edit to the server voxel map, and logs it for the clients loading its chunk later on.
+fn apply_voxel_edit(
+    edit: VoxelEdit,
+    edit_log: &mut VoxelEditLog,
+    chunks: &mut ChunkMap<Voxel, ChunkShape>,
+    fluids: &mut FluidSimulation,
+) {
+    edit_log.record(edit);
+    if let Some(mut voxel) = chunks.voxel_at_mut(edit.position) {
+        *voxel = edit.voxel;
+    }
+    fluids.activate(edit.position);
+}
+
+/// Rebroadcasts the edits accepted from a client to the other clients.
+/// The client already applied the rejected edits, so it is sent back the voxels it should hold instead,
+/// except in the chunks the server doesn't hold, which are out of the reach of its player.
+fn answer_voxel_edits(
+    server: &mut RenetServer,
+    client_id: u64,
+    accepted: Vec<VoxelEdit>,
+    rejected: Vec<VoxelEdit>,
+    chunks: &ChunkMap<Voxel, ChunkShape>,
+    edit_log: &VoxelEditLog,
+) {
+    let corrections = VoxelEditBatch {
+        edits: rejected
+            .into_iter()
+            .map(|edit| edit.position)
+            .collect::<HashSet<IVec3>>()
+            .into_iter()
+            .filter_map(|position| {
+                Some(VoxelEdit {
+                    position,
+                    voxel: authoritative_voxel(chunks, edit_log, position)?,
+                })
+            })
+            .collect(),
+    };
+    if !corrections.edits.is_empty() {
+        let message = bincode::serialize(&corrections).unwrap();
+        server.send_message(client_id, ServerChannel::VoxelEdits, message);
+    }
+
+    if !accepted.is_empty() {
+        let message = bincode::serialize(&VoxelEditBatch { edits: accepted }).unwrap();
+        server.broadcast_message_except(client_id, ServerChannel::VoxelEdits, message);
+    }
+}
+
+/// Validates, applies and rebroadcasts the voxel edits made by the clients, and answers their requests for the edits of loaded chunks.
+#[allow(clippy::too_many_arguments)]
+fn server_voxel_edits_system(
+    mut server: ResMut<RenetServer>,
+    mut edit_log: ResMut<VoxelEditLog>,
+    mut chunks: ResMut<ChunkMap<Voxel, ChunkShape>>,
+    mut fluids: ResMut<FluidSimulation>,
+    mut saved_chunk_requests: ResMut<world::SavedChunkRequests>,
+    lobby: Res<ServerLobby>,
+    players: Query<&Transform, With<Player>>,
+) {
+    for client_id in server.clients_id() {
+        while let Some(message) = server.receive_message(client_id, ClientChannel::VoxelEdits) {
+            let Ok(batch) = bincode::deserialize::<VoxelEditBatch>(&message) else {
+                continue;
+            };
+            let Some(player_transform) = lobby
+                .players
+                .get(&client_id)
+                .and_then(|entity| players.get(*entity).ok())
+            else {
+                continue;
+            };
+
+            let (accepted, rejected): (Vec<VoxelEdit>, Vec<VoxelEdit>) =
+                batch.edits.into_iter().partition(|edit| {
+                    edit.position.y >= WORLD_BOTTOM_BORDER_HEIGHT as i32
+                        && edit
+                            .position
+                            .as_vec3()
+                            .distance(player_transform.translation)
+                            <= MAX_VOXEL_EDIT_DISTANCE
+                        && chunks.voxel_at(edit.position).map(|voxel| voxel.id) != Some(Bedrock::ID)
+                });
+
+            for edit in accepted.iter() {
+                apply_voxel_edit(*edit, &mut edit_log, &mut chunks, &mut fluids);
+            }
+            answer_voxel_edits(
+                &mut server,
+                client_id,
+                accepted,
+                rejected,
+                &chunks,
+                &edit_log,
+            );
+        }
+
+        while let Some(message) = server.receive_message(client_id, ClientChannel::ChunkEdits) {
+            let Ok(request) = bincode::deserialize::<ChunkEditsRequest>(&message) else {
+                continue;
+            };
+
+            // the edits of the saved chunks were dropped from the log, these chunks are sent in full once loaded.
+            let (saved, logged): (Vec<IVec3>, Vec<IVec3>) = request
+                .chunks
+                .into_iter()
+                .partition(|chunk| edit_log.is_saved(*chunk));
+            for chunk in saved {
+                saved_chunk_requests.request(chunk, client_id);
+            }
+
+            let edits = VoxelEditBatch {
+                edits: logged
+                    .into_iter()
+                    .flat_map(|chunk| edit_log.chunk_edits(chunk))
+                    .collect(),
+            };
+
+            if !edits.edits.is_empty() {
+                let message = bincode::serialize(&edits).unwrap();
+                server.send_message(client_id, ServerChannel::VoxelEdits, message);
+            }
+        }
+    }
+}
+
+/// Advances the time of day, and regularly broadcasts it to the clients.
+fn server_time_of_day_system(
+    time: Res<Time>,
+    mut server: ResMut<RenetServer>,
+    mut time_of_day: ResMut<TimeOfDay>,
+    mut since_sync: Local<f32>,
+) {
+    time_of_day.advance(time.delta_seconds());
+
+    *since_sync += time.delta_seconds();
+    if *since_sync < TIME_OF_DAY_SYNC_INTERVAL {
+        return;
+    }
+    *since_sync = 0.0;
+
+    let message = bincode::serialize(&ServerMessages::TimeOfDay {
+        time_of_day: *time_of_day,
+    })
+    .unwrap();
+    server.broadcast_message(ServerChannel::ServerMessages, message);
+}
+
+/// Returns whether a voxel holds up the voxels of the gravity affected materials, as opposed to empty voxels and liquids.
+fn supports(fluids: &FluidSimulation, voxel: Voxel) -> bool {
+    !voxel.is_empty() && !fluids.is_liquid(voxel)
+}
+
+/// Returns the voxel falling from the origin of a fall if the fall is possible in the server world,
+/// which is when a gravity affected voxel there has nothing holding it up down to its landing, in the reach of the player.
+fn checked_fall(
+    fall: &VoxelFall,
+    chunks: &ChunkMap<Voxel, ChunkShape>,
+    fluids: &FluidSimulation,
+    gravity: &GravityMaterials,
+    player_position: Vec3,
+) -> Option<Voxel> {
+    let voxel = chunks.voxel_at(fall.origin)?;
+    let valid = fall.landing.x == fall.origin.x
+        && fall.landing.z == fall.origin.z
+        && fall.landing.y < fall.origin.y
+        && fall.landing.y >= WORLD_BOTTOM_BORDER_HEIGHT as i32
+        && fall.origin.as_vec3().distance(player_position) <= MAX_VOXEL_FALL_REACH
+        && gravity.0.contains(&voxel.id)
+        && (fall.landing.y..fall.origin.y).all(|y| {
+            chunks
+                .voxel_at(IVec3::new(fall.origin.x, y, fall.origin.z))
+                .is_some_and(|voxel| !supports(fluids, voxel))
+        });
+    valid.then_some(voxel)
+}
+
+/// Applies and rebroadcasts the voxels the clients saw falling by gravity, as the edits emptying their origin and filling their landing.
+/// Columns collapse above the voxels the players edit and falling voxels land below them, so the falls are checked against
+/// the server world rather than the reach of the player edits.
+fn server_voxel_falls_system(
+    mut server: ResMut<RenetServer>,
+    mut edit_log: ResMut<VoxelEditLog>,
+    mut chunks: ResMut<ChunkMap<Voxel, ChunkShape>>,
+    mut fluids: ResMut<FluidSimulation>,
+    gravity: Res<GravityMaterials>,
+    lobby: Res<ServerLobby>,
+    players: Query<&Transform, With<Player>>,
+) {
+    for client_id in server.clients_id() {
+        while let Some(message) = server.receive_message(client_id, ClientChannel::VoxelFalls) {
+            let Ok(mut batch) = bincode::deserialize::<VoxelFallBatch>(&message) else {
+                continue;
+            };
+            let Some(player_transform) = lobby
+                .players
+                .get(&client_id)
+                .and_then(|entity| players.get(*entity).ok())
+            else {
+                continue;
+            };
+
+            // the voxels of a collapsed column fall through the origins of the ones below them.
+            batch.falls.sort_unstable_by_key(|fall| fall.origin.y);
+
+            let (mut accepted, mut rejected) = (Vec::new(), Vec::new());
+            for fall in batch.falls {
+                let edits = [
+                    VoxelEdit {
+                        position: fall.origin,
+                        voxel: Voxel::EMPTY_VOXEL,
+                    },
+                    VoxelEdit {
+                        position: fall.landing,
+                        voxel: fall.voxel,
+                    },
+                ];
+
+                let Some(voxel) = checked_fall(
+                    &fall,
+                    &chunks,
+                    &fluids,
+                    &gravity,
+                    player_transform.translation,
+                ) else {
+                    rejected.extend(edits);
+                    continue;
+                };
+
+                for edit in [edits[0], VoxelEdit { voxel, ..edits[1] }] {
+                    apply_voxel_edit(edit, &mut edit_log, &mut chunks, &mut fluids);
+                    accepted.push(edit);
+                }
+            }
+
+            answer_voxel_edits(
+                &mut server,
+                client_id,
+                accepted,
+                rejected,
+                &chunks,
+                &edit_log,
+            );
+        }
+    }
+}
+
+/// Ids of the materials whose voxels fall down when nothing holds them up.
+#[derive(Debug, Default, Resource)]
+struct GravityMaterials(Vec<u16>);
+
+/// Voxels the liquids changed which may still change, logged once the liquids around them settle.
+#[derive(Debug, Default, Resource)]
+struct UnsettledFluids(HashSet<IVec3>);
+
+/// Reads the liquids and the gravity affected materials from the material definitions shared with the clients.
+fn setup_material_flags(
+    mut fluids: ResMut<FluidSimulation>,
+    mut gravity: ResMut<GravityMaterials>,
+) {
+    let path = Path::new(&asset_folder_from_args()).join(MATERIAL_DEFINITIONS_PATH);
+    let result = std::fs::read(&path)
+        .map_err(|err| err.to_string())
+        .and_then(|bytes| {
+            fluids
+                .set_liquids_from_definitions(&bytes)
+                .map_err(|err| err.to_string())?;
+            gravity.0 = material_ids_with_flags(&bytes, VoxelMaterialFlags::GRAVITY)
+                .map_err(|err| err.to_string())?;
+            Ok(())
+        });
+
+    if let Err(err) = result {
+        warn!(
+            "cannot read the material flags from {}: {err}",
+            path.display()
+        );
+    }
+}
+
+/// Steps the fluid simulation at a fixed rate and broadcasts the voxels the liquids changed.
+/// The server is the only one simulating the liquids, so the server world loads the chunks they flow through
+/// even when no client streams them from the server, and the liquids next to these chunks wait until they are loaded.
+/// The changed voxels are only logged once they settle, for the clients loading their chunks later on.
+fn server_fluids_system(
+    time: Res<Time>,
+    mut server: ResMut<RenetServer>,
+    mut edit_log: ResMut<VoxelEditLog>,
+    mut chunks: ResMut<ChunkMap<Voxel, ChunkShape>>,
+    mut fluids: ResMut<FluidSimulation>,
+    mut unsettled: ResMut<UnsettledFluids>,
+    mut elapsed: Local<f32>,
+) {
+    *elapsed += time.delta_seconds();
+    if *elapsed < FLUID_TICK_INTERVAL {
+        return;
+    }
+    *elapsed = (*elapsed - FLUID_TICK_INTERVAL).min(FLUID_TICK_INTERVAL);
+
+    // the missing chunks are being loaded by the server world, the liquids wait for them.
+    let loading: HashSet<IVec3> = world::fluid_chunks(&fluids)
+        .into_iter()
+        .filter(|key| !chunks.exists(*key))
+        .collect();
+
+    let edits = VoxelEditBatch {
+        edits: fluids.tick(&mut chunks, |key| loading.contains(&key)),
+    };
+
+    unsettled
+        .0
+        .extend(edits.edits.iter().map(|edit| edit.position));
+    unsettled.0.retain(|position| {
+        if fluids.is_active(*position) {
+            return true;
+        }
+
+        // the liquids drying out where nothing was logged leave the voxel empty, as it was generated.
+        if let Some(voxel) = chunks.voxel_at(*position) {
+            if !voxel.is_empty() || edit_log.voxel_at(*position).is_some() {
+                edit_log.record(VoxelEdit {
+                    position: *position,
+                    voxel,
+                });
+            }
+        }
+        false
+    });
+
+    if edits.edits.is_empty() {
+        return;
+    }
+
+    let message = bincode::serialize(&edits).unwrap();
+    server.broadcast_message(ServerChannel::VoxelEdits, message);
+}
+
 #[allow(clippy::type_complexity)]
 fn server_network_sync(
     mut server: ResMut<RenetServer>,
@@ -0,0 +1,401 @@
+use bevy::{
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, Task},
+    utils::{HashMap, HashSet},
+};
+use bevy_renet::renet::{RenetServer, ServerEvent};
+use common::{
+    voxel::{
+        chunks_in_view,
+        fluids::{neighbourhood_chunks, FluidSimulation},
+        storage::{encode_chunk, ChunkMap, PaletteBuffer, RegionStore, VoxelBuffer},
+        terraingen::{
+            biome_definitions_loaded, water::SeaLevel, TerrainGeneratorPlugin, WorldSeed,
+            TERRAIN_GENERATOR, TERRAIN_MAX_HEIGHT,
+        },
+        ChunkShape, Voxel,
+    },
+    ChunkPayload, ChunkViewRadius, ChunksUnloaded, ClientChannel, Player, ServerChannel,
+    CHUNK_LENGTH,
+};
+use float_ord::FloatOrd;
+use futures_lite::future;
+use std::path::Path;
+
+use crate::{ServerLobby, VoxelEditLog, MAX_VOXEL_FALL_REACH};
+
+/// Directory where the region files of the server worlds are saved, in a subdirectory per seed.
+pub const SERVER_WORLD_SAVE_DIR: &str = "saves/server_world";
+
+/// Maximum number of chunks being generated or loaded from disk at once.
+const MAX_CHUNK_TASKS: usize = 64;
+/// Maximum number of encoded chunk bytes queued per client and per tick, so a client's channel never overflows.
+const CHUNK_SEND_BUDGET: usize = 256 * 1024;
+
+/// The chunks a client is subscribed to.
+struct ChunkStream {
+    radius: ChunkViewRadius,
+    center: Option<IVec3>,
+    view: HashSet<IVec3>,
+    /// Chunks in view not sent yet, closest first.
+    queue: Vec<IVec3>,
+    sent: HashSet<IVec3>,
+}
+
+/// Per client chunk streaming state.
+#[derive(Default, Resource)]
+struct ChunkStreams(HashMap<u64, ChunkStream>);
+
+/// Chunks currently being generated or loaded from disk.
+#[derive(Default, Resource)]
+struct ChunkTasks(HashMap<IVec3, Task<PaletteBuffer<Voxel, ChunkShape>>>);
+
+/// Saved chunks requested by clients generating their terrain themselves, with the clients waiting for each of them.
+/// Their edits were dropped from the edit log when they were saved, so they are sent in full once loaded back.
+#[derive(Default, Resource)]
+pub struct SavedChunkRequests(HashMap<IVec3, HashSet<u64>>);
+
+impl SavedChunkRequests {
+    /// Sends the saved chunk to the client once it is loaded back.
+    pub fn request(&mut self, key: IVec3, client_id: u64) {
+        self.0.entry(key).or_default().insert(client_id);
+    }
+}
+
+/// Seeds the terrain generator and opens the region files of the world matching the seed.
+fn setup_world_seed(mut commands: Commands, seed: Res<WorldSeed>, sea_level: Res<SeaLevel>) {
+    TERRAIN_GENERATOR
+        .write()
+        .unwrap()
+        .set_seed(*seed)
+        .set_sea_level(sea_level.0);
+    commands.insert_resource(RegionStore::new(
+        Path::new(SERVER_WORLD_SAVE_DIR).join(seed.0.to_string()),
+    ));
+}
+
+/// Subscribes clients to the chunks around them when they send their view radius.
+fn receive_chunk_view_radius(
+    mut server: ResMut<RenetServer>,
+    mut streams: ResMut<ChunkStreams>,
+    mut server_events: EventReader<ServerEvent>,
+) {
+    for event in server_events.iter() {
+        if let ServerEvent::ClientDisconnected { client_id, .. } = event {
+            streams.0.remove(client_id);
+        }
+    }
+
+    for client_id in server.clients_id() {
+        while let Some(message) = server.receive_message(client_id, ClientChannel::ChunkView) {
+            let Ok(radius) = bincode::deserialize::<ChunkViewRadius>(&message) else {
+                continue;
+            };
+
+            let stream = streams.0.entry(client_id).or_insert_with(|| ChunkStream {
+                radius,
+                center: None,
+                view: HashSet::default(),
+                queue: Vec::new(),
+                sent: HashSet::default(),
+            });
+
+            stream.radius = radius;
+            // force the view to be computed again.
+            stream.center = None;
+        }
+    }
+}
+
+/// Forgets the chunks the clients dropped, so the ones still in view are streamed again.
+fn receive_unloaded_chunks(mut server: ResMut<RenetServer>, mut streams: ResMut<ChunkStreams>) {
+    for client_id in server.clients_id() {
+        while let Some(message) = server.receive_message(client_id, ClientChannel::ChunkUnload) {
+            let Ok(unloaded) = bincode::deserialize::<ChunksUnloaded>(&message) else {
+                continue;
+            };
+            let Some(stream) = streams.0.get_mut(&client_id) else {
+                continue;
+            };
+
+            let mut requeued = false;
+            for key in unloaded.chunks {
+                if stream.sent.remove(&key) && stream.view.contains(&key) {
+                    stream.queue.push(key);
+                    requeued = true;
+                }
+            }
+
+            if let (true, Some(center)) = (requeued, stream.center) {
+                stream
+                    .queue
+                    .sort_unstable_by_key(|key| FloatOrd(key.as_vec3().distance(center.as_vec3())));
+            }
+        }
+    }
+}
+
+/// Recomputes the chunks in view of the clients which moved to another chunk.
+fn update_chunk_streams(
+    mut streams: ResMut<ChunkStreams>,
+    lobby: Res<ServerLobby>,
+    players: Query<&Transform, With<Player>>,
+) {
+    for (client_id, stream) in streams.0.iter_mut() {
+        let Some(player_transform) = lobby
+            .players
+            .get(client_id)
+            .and_then(|entity| players.get(*entity).ok())
+        else {
+            continue;
+        };
+
+        let center =
+            !IVec3::splat(CHUNK_LENGTH as i32 - 1) & player_transform.translation.as_ivec3();
+        if stream.center == Some(center) {
+            continue;
+        }
+
+        stream.center = Some(center);
+        stream.view =
+            chunks_in_view(center, stream.radius.horizontal, stream.radius.vertical).collect();
+
+        let view = &stream.view;
+        stream.sent.retain(|key| view.contains(key));
+        stream.queue = view
+            .iter()
+            .filter(|key| !stream.sent.contains(*key))
+            .copied()
+            .collect();
+        stream
+            .queue
+            .sort_unstable_by_key(|key| FloatOrd(key.as_vec3().distance(center.as_vec3())));
+    }
+}
+
+/// Loads the chunk at the specified minimum back from disk, or generates it if it was never saved.
+pub fn load_or_generate_chunk(
+    region_store: &RegionStore,
+    key: IVec3,
+) -> PaletteBuffer<Voxel, ChunkShape> {
+    if let Some(chunk_data) = region_store.load(key) {
+        return chunk_data;
+    }
+
+    let mut chunk_data = VoxelBuffer::<Voxel, ChunkShape>::new_empty(ChunkShape {});
+    if key.y < TERRAIN_MAX_HEIGHT {
+        TERRAIN_GENERATOR
+            .read()
+            .unwrap()
+            .generate(key, &mut chunk_data);
+    }
+    PaletteBuffer::from(chunk_data)
+}
+
+/// Queues the generation of the chunks waiting to be streamed, starting from the closest ones.
+/// The chunks in reach of the players are loaded as well, to check the voxels they see falling.
+/// Chunks previously saved to disk are loaded back instead of being generated.
+/// Nothing is generated before the biome definitions are loaded.
+#[allow(clippy::too_many_arguments)]
+fn queue_chunk_tasks(
+    streams: Res<ChunkStreams>,
+    saved_chunk_requests: Res<SavedChunkRequests>,
+    fluids: Res<FluidSimulation>,
+    lobby: Res<ServerLobby>,
+    players: Query<&Transform, With<Player>>,
+    chunks: Res<ChunkMap<Voxel, ChunkShape>>,
+    region_store: Res<RegionStore>,
+    mut tasks: ResMut<ChunkTasks>,
+) {
+    let task_pool = AsyncComputeTaskPool::get();
+
+    let queued = streams
+        .0
+        .values()
+        .flat_map(|stream| stream.queue.iter().copied())
+        .chain(saved_chunk_requests.0.keys().copied())
+        .chain(fluid_chunks(&fluids))
+        .chain(reach_chunks(&lobby, &players));
+
+    for key in queued {
+        if tasks.0.len() >= MAX_CHUNK_TASKS {
+            return;
+        }
+
+        if chunks.buffer_at(key).is_some() || tasks.0.contains_key(&key) {
+            continue;
+        }
+
+        let region_store = region_store.clone();
+        let task = task_pool.spawn(async move { load_or_generate_chunk(&region_store, key) });
+        tasks.0.insert(key, task);
+    }
+}
+
+/// Polls for finished chunk tasks and puts the chunks into the server voxel map, with the logged edits applied.
+fn process_chunk_tasks(
+    mut tasks: ResMut<ChunkTasks>,
+    mut chunks: ResMut<ChunkMap<Voxel, ChunkShape>>,
+    edit_log: Res<VoxelEditLog>,
+) {
+    tasks.0.retain(|key, task| {
+        let Some(mut data) = future::block_on(future::poll_once(task)) else {
+            return true;
+        };
+
+        for edit in edit_log.chunk_edits(*key) {
+            data.set_voxel((edit.position - *key).as_uvec3(), edit.voxel);
+        }
+        chunks.insert(*key, data);
+        false
+    });
+}
+
+/// Sends the requested saved chunks which are loaded to the clients waiting for them.
+fn send_saved_chunks(
+    mut server: ResMut<RenetServer>,
+    mut saved_chunk_requests: ResMut<SavedChunkRequests>,
+    chunks: Res<ChunkMap<Voxel, ChunkShape>>,
+) {
+    saved_chunk_requests.0.retain(|key, clients| {
+        let Some(buffer) = chunks.buffer_at(*key) else {
+            return true;
+        };
+
+        let payload = ChunkPayload {
+            key: *key,
+            data: encode_chunk(&buffer.to_buffer()),
+        };
+        let message = bincode::serialize(&payload).unwrap();
+        for client_id in clients.iter() {
+            if server.is_connected(*client_id) {
+                server.send_message(*client_id, ServerChannel::ChunkData, message.clone());
+            }
+        }
+        false
+    });
+}
+
+/// Sends the loaded chunks waiting in each client's queue, closest first.
+fn send_chunks(
+    mut server: ResMut<RenetServer>,
+    mut streams: ResMut<ChunkStreams>,
+    chunks: Res<ChunkMap<Voxel, ChunkShape>>,
+) {
+    for (client_id, stream) in streams.0.iter_mut() {
+        let mut budget = CHUNK_SEND_BUDGET;
+        let mut sent = Vec::new();
+
+        for key in stream.queue.iter().copied() {
+            if budget == 0 {
+                break;
+            }
+
+            let Some(buffer) = chunks.buffer_at(key) else {
+                continue;
+            };
+
+            let payload = ChunkPayload {
+                key,
+                data: encode_chunk(&buffer.to_buffer()),
+            };
+            let message = bincode::serialize(&payload).unwrap();
+            budget = budget.saturating_sub(message.len());
+            server.send_message(*client_id, ServerChannel::ChunkData, message);
+            sent.push(key);
+        }
+
+        stream.queue.retain(|key| !sent.contains(key));
+        stream.sent.extend(sent);
+    }
+}
+
+/// Unloads the chunks no client is looking at anymore and writes them to disk, along their logged edits.
+/// The chunks holding liquids waiting to flow stay loaded until the liquids settle, and the ones in reach of the players stay loaded.
+fn unload_chunks(
+    streams: Res<ChunkStreams>,
+    lobby: Res<ServerLobby>,
+    players: Query<&Transform, With<Player>>,
+    mut chunks: ResMut<ChunkMap<Voxel, ChunkShape>>,
+    region_store: Res<RegionStore>,
+    fluids: Res<FluidSimulation>,
+    mut edit_log: ResMut<VoxelEditLog>,
+) {
+    let flowing = fluid_chunks(&fluids);
+    let reached = reach_chunks(&lobby, &players);
+    let unused: Vec<IVec3> = chunks
+        .iter_keys()
+        .filter(|key| !streams.0.values().any(|stream| stream.view.contains(key)))
+        .filter(|key| !flowing.contains(key) && !reached.contains(key))
+        .collect();
+
+    for key in unused {
+        if let Some(buffer) = chunks.remove(key) {
+            region_store.queue_save(key, buffer);
+            edit_log.forget_saved_chunk(key);
+        }
+    }
+
+    region_store.flush_in_background();
+}
+
+/// Returns the chunks the fluid simulation reads while updating the voxels waiting to be updated,
+/// which are kept loaded until the liquids settle.
+pub fn fluid_chunks(fluids: &FluidSimulation) -> HashSet<IVec3> {
+    fluids
+        .iter_active()
+        .flat_map(neighbourhood_chunks)
+        .filter(|key| key.y >= 0)
+        .collect()
+}
+
+/// Returns the chunks within [`MAX_VOXEL_FALL_REACH`] of the players, where the voxels they see falling are checked.
+fn reach_chunks(lobby: &ServerLobby, players: &Query<&Transform, With<Player>>) -> HashSet<IVec3> {
+    let mask = !IVec3::splat(CHUNK_LENGTH as i32 - 1);
+    let reach = Vec3::splat(MAX_VOXEL_FALL_REACH);
+
+    let mut keys = HashSet::default();
+    for entity in lobby.players.values() {
+        let Ok(transform) = players.get(*entity) else {
+            continue;
+        };
+
+        let min = mask & (transform.translation - reach).floor().as_ivec3();
+        let max = mask & (transform.translation + reach).floor().as_ivec3();
+        for z in (min.z..=max.z).step_by(CHUNK_LENGTH as usize) {
+            for y in (min.y.max(0)..=max.y).step_by(CHUNK_LENGTH as usize) {
+                for x in (min.x..=max.x).step_by(CHUNK_LENGTH as usize) {
+                    keys.insert(IVec3::new(x, y, z));
+                }
+            }
+        }
+    }
+    keys
+}
+
+/// Generates the world on the server and streams its chunks to the clients subscribed to them.
+pub struct ServerWorldPlugin;
+
+impl Plugin for ServerWorldPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ChunkMap::<Voxel, ChunkShape>::new(ChunkShape {}))
+            .init_resource::<ChunkStreams>()
+            .init_resource::<ChunkTasks>()
+            .init_resource::<SavedChunkRequests>()
+            .add_plugin(TerrainGeneratorPlugin)
+            .add_startup_system(setup_world_seed)
+            .add_systems(
+                (
+                    receive_chunk_view_radius,
+                    receive_unloaded_chunks,
+                    update_chunk_streams,
+                    queue_chunk_tasks.run_if(biome_definitions_loaded),
+                    process_chunk_tasks,
+                    send_saved_chunks,
+                    send_chunks,
+                    unload_chunks,
+                )
+                    .chain(),
+            );
+    }
+}